@@ -1,21 +1,41 @@
 //! Contains the implementation to extract the database of all aircrafts available in ADS-B exchange
 //! The database contains "current" status.
 use std::error::Error;
-use std::{collections::HashMap, sync::Arc};
-
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+};
+
+use arrow::array::{Array, ArrayRef, StringArray};
+use arrow::datatypes::{DataType, Field, Schema};
+use arrow::record_batch::RecordBatch;
 use async_recursion::async_recursion;
 use futures::{StreamExt, TryStreamExt};
-use reqwest;
+use parquet::arrow::arrow_reader::{ParquetRecordBatchReaderBuilder, ProjectionMask};
+use parquet::arrow::ArrowWriter;
+use parquet::basic::Compression;
+use parquet::file::metadata::RowGroupMetaData;
+use parquet::file::properties::WriterProperties;
+use parquet::file::statistics::Statistics;
+use parquet::schema::types::ColumnPath;
 use serde::{Deserialize, Serialize};
 use serde_json;
 use time::Date;
 
-use crate::csv;
 use crate::fs::BlobStorageProvider;
 use crate::CountryIcaoRanges;
 
 static DATABASE: &'static str = "aircraft/db/";
 
+/// Column names of the aircraft Parquet schema, in [`aircraft_schema`] order.
+pub(crate) const COLUMNS: [&str; 5] = [
+    "icao_number",
+    "tail_number",
+    "type_designator",
+    "model",
+    "country",
+];
+
 /// [`HashMap`] between tail number (e.g. "OY-TWM") and an [`Aircraft`]
 pub type Aircrafts = HashMap<Arc<str>, Aircraft>;
 
@@ -34,12 +54,91 @@ pub struct Aircraft {
     pub country: Option<Arc<str>>,
 }
 
+impl Aircraft {
+    /// Returns this aircraft's value for one of [`COLUMNS`], by name. Used to apply a `filter` in
+    /// memory on the legacy `v0` read path, which predates Parquet row-group statistics, and by
+    /// [`crate::filter`]'s compiled predicates.
+    pub(crate) fn column(&self, name: &str) -> &str {
+        match name {
+            "icao_number" => &self.icao_number,
+            "tail_number" => &self.tail_number,
+            "type_designator" => &self.type_designator,
+            "model" => &self.model,
+            "country" => self.country.as_deref().unwrap_or(""),
+            other => panic!("unknown aircraft column {other}"),
+        }
+    }
+
+    /// Blanks out every field not in `projection`, matching [`read_parquet`]'s "column left out
+    /// of the projection comes back empty" contract, for the legacy `v0` read path.
+    fn project(mut self, projection: Option<&[&str]>) -> Self {
+        let Some(projection) = projection else {
+            return self;
+        };
+        if !projection.contains(&COLUMNS[0]) {
+            self.icao_number = "".into();
+        }
+        if !projection.contains(&COLUMNS[1]) {
+            self.tail_number = String::new();
+        }
+        if !projection.contains(&COLUMNS[2]) {
+            self.type_designator = String::new();
+        }
+        if !projection.contains(&COLUMNS[3]) {
+            self.model = String::new();
+        }
+        if !projection.contains(&COLUMNS[4]) {
+            self.country = None;
+        }
+        self
+    }
+}
+
+/// The pre-versioning (`v0`) CSV row shape. Identical to [`Aircraft`] today, since the only
+/// change `v1` made was the storage encoding (CSV -> Parquet); kept as its own type so a future
+/// schema change has a natural place to diverge it from `Aircraft` and backfill defaults in
+/// [`upgrade_v0`].
+#[derive(Deserialize)]
+struct AircraftV0 {
+    icao_number: Arc<str>,
+    tail_number: String,
+    type_designator: String,
+    model: String,
+    country: Option<Arc<str>>,
+}
+
+fn upgrade_v0(row: AircraftV0) -> Aircraft {
+    Aircraft {
+        icao_number: row.icao_number,
+        tail_number: row.tail_number,
+        type_designator: row.type_designator,
+        model: row.model,
+        country: row.country,
+    }
+}
+
+/// The schema version `etl_aircrafts` always writes, encoded in the blob path (e.g.
+/// `data-v1.parquet`) so that old and new partitions can coexist during a migration. `read`
+/// transparently falls back to, and upgrades, the unversioned `v0` CSV blobs written before this
+/// scheme existed (see [`read_filtered`]).
+const CURRENT_VERSION: u32 = 1;
+
 fn pk_to_blob_name(date: &time::Date) -> String {
-    format!("{DATABASE}date={date}/data.csv",)
+    format!("{DATABASE}date={date}/data-v{CURRENT_VERSION}.parquet")
+}
+
+/// The blob name of the pre-versioning (`v0`) CSV snapshot for `date`, still present for
+/// partitions written before [`CURRENT_VERSION`] was introduced.
+fn legacy_v0_blob_name(date: &time::Date) -> String {
+    format!("{DATABASE}date={date}/data.csv")
 }
 
+/// Extracts the `date=` hive key from a blob name, regardless of its filename (`data.csv`,
+/// `data-v1.parquet`, ...), so [`read_all`] can enumerate dates across schema versions.
 fn blob_name_to_pk(blob: &str) -> time::Date {
-    let mut keys = crate::serde::hive_to_map(&blob[DATABASE.len()..blob.len() - "data.csv".len()]);
+    let hive_part = &blob[DATABASE.len()..];
+    let hive_part = &hive_part[..hive_part.rfind('/').map_or(0, |i| i + 1)];
+    let mut keys = crate::serde::hive_to_map(hive_part);
     let date = keys.remove("date").unwrap();
     time::Date::parse(
         date,
@@ -48,6 +147,142 @@ fn blob_name_to_pk(blob: &str) -> time::Date {
     .unwrap()
 }
 
+/// The Arrow schema used to read and write [`Aircraft`] as Parquet, field-for-field in
+/// declaration order. `country` is nullable; the rest are always present.
+fn aircraft_schema() -> Schema {
+    Schema::new(vec![
+        Field::new(COLUMNS[0], DataType::Utf8, false),
+        Field::new(COLUMNS[1], DataType::Utf8, false),
+        Field::new(COLUMNS[2], DataType::Utf8, false),
+        Field::new(COLUMNS[3], DataType::Utf8, false),
+        Field::new(COLUMNS[4], DataType::Utf8, true),
+    ])
+}
+
+fn aircraft_to_record_batch(aircraft: &[Aircraft]) -> Result<RecordBatch, Box<dyn Error>> {
+    let columns: Vec<ArrayRef> = vec![
+        Arc::new(StringArray::from_iter_values(
+            aircraft.iter().map(|a| a.icao_number.as_ref()),
+        )),
+        Arc::new(StringArray::from_iter_values(
+            aircraft.iter().map(|a| a.tail_number.as_str()),
+        )),
+        Arc::new(StringArray::from_iter_values(
+            aircraft.iter().map(|a| a.type_designator.as_str()),
+        )),
+        Arc::new(StringArray::from_iter_values(
+            aircraft.iter().map(|a| a.model.as_str()),
+        )),
+        Arc::new(StringArray::from_iter(
+            aircraft.iter().map(|a| a.country.as_deref()),
+        )),
+    ];
+    Ok(RecordBatch::try_new(Arc::new(aircraft_schema()), columns)?)
+}
+
+/// Serializes `aircraft` as Parquet, with dictionary encoding on `type_designator`, `model` and
+/// `country`: every aircraft shares one of a few hundred models, so dictionary-encoding those
+/// columns (instead of `icao_number`/`tail_number`, which are unique per row) keeps the file
+/// small without giving up per-row granularity.
+fn write_parquet(aircraft: &[Aircraft]) -> Result<Vec<u8>, Box<dyn Error>> {
+    let batch = aircraft_to_record_batch(aircraft)?;
+
+    let properties = WriterProperties::builder()
+        .set_compression(Compression::SNAPPY)
+        .set_column_dictionary_enabled(ColumnPath::from(COLUMNS[2]), true)
+        .set_column_dictionary_enabled(ColumnPath::from(COLUMNS[3]), true)
+        .set_column_dictionary_enabled(ColumnPath::from(COLUMNS[4]), true)
+        .build();
+    let mut buffer = Vec::new();
+    let mut writer = ArrowWriter::try_new(&mut buffer, batch.schema(), Some(properties))?;
+    writer.write(&batch)?;
+    writer.close()?;
+    Ok(buffer)
+}
+
+/// Returns the indices of `row_groups` whose `column_index`'s min/max statistics can still match
+/// `value`, so [`read_parquet`] can skip decoding row groups that provably don't contain it.
+fn matching_row_groups(
+    row_groups: &[RowGroupMetaData],
+    column_index: usize,
+    value: &str,
+) -> Vec<usize> {
+    row_groups
+        .iter()
+        .enumerate()
+        .filter(|(_, group)| {
+            let Some(stats) = group.column(column_index).statistics() else {
+                return true; // no stats: can't rule the group out
+            };
+            match stats {
+                Statistics::ByteArray(stats) => {
+                    let (Some(min), Some(max)) = (stats.min_opt(), stats.max_opt()) else {
+                        return true;
+                    };
+                    min.as_bytes() <= value.as_bytes() && value.as_bytes() <= max.as_bytes()
+                }
+                _ => true, // not a byte-array (string) column: can't rule the group out
+            }
+        })
+        .map(|(i, _)| i)
+        .collect()
+}
+
+/// Deserializes a Parquet file previously written by [`write_parquet`], optionally projecting
+/// only `columns` (by name, from [`COLUMNS`]) and pushing an equality `filter` (column name,
+/// value) down to row-group statistics so non-matching row groups are never decoded. Columns
+/// left out of the projection come back as empty strings / `None`.
+fn read_parquet(
+    data: Vec<u8>,
+    columns: Option<&[&str]>,
+    filter: Option<(&str, &str)>,
+) -> Result<Vec<Aircraft>, Box<dyn Error>> {
+    let mut builder = ParquetRecordBatchReaderBuilder::try_new(bytes::Bytes::from(data))?;
+
+    if let Some((column, value)) = filter {
+        let column_index = COLUMNS.iter().position(|c| *c == column).expect("known column");
+        let row_groups = matching_row_groups(builder.metadata().row_groups(), column_index, value);
+        builder = builder.with_row_groups(row_groups);
+    }
+
+    if let Some(columns) = columns {
+        let indices = columns
+            .iter()
+            .map(|c| COLUMNS.iter().position(|x| x == c).expect("known column"))
+            .collect::<Vec<_>>();
+        let mask = ProjectionMask::leaves(builder.parquet_schema(), indices);
+        builder = builder.with_projection(mask);
+    }
+
+    let reader = builder.build()?;
+
+    let mut aircraft = Vec::new();
+    for batch in reader {
+        let batch = batch?;
+        let field = |name: &str| {
+            batch
+                .column_by_name(name)
+                .map(|c| c.as_any().downcast_ref::<StringArray>().unwrap())
+        };
+        let icao_number = field(COLUMNS[0]);
+        let tail_number = field(COLUMNS[1]);
+        let type_designator = field(COLUMNS[2]);
+        let model = field(COLUMNS[3]);
+        let country = field(COLUMNS[4]);
+
+        for row in 0..batch.num_rows() {
+            aircraft.push(Aircraft {
+                icao_number: icao_number.map_or("", |c| c.value(row)).into(),
+                tail_number: tail_number.map_or("", |c| c.value(row)).to_string(),
+                type_designator: type_designator.map_or("", |c| c.value(row)).to_string(),
+                model: model.map_or("", |c| c.value(row)).to_string(),
+                country: country.and_then(|c| (!c.is_null(row)).then(|| c.value(row).into())),
+            });
+        }
+    }
+    Ok(aircraft)
+}
+
 fn url(prefix: &str) -> String {
     format!("https://globe.adsbexchange.com/db-current/{prefix}.js")
 }
@@ -57,7 +292,9 @@ fn url(prefix: &str) -> String {
 async fn db_current(
     prefix: String,
 ) -> Result<(String, HashMap<String, Vec<Option<String>>>), String> {
-    let data = reqwest::get(url(&prefix))
+    let data = crate::icao_to_trace::http_client()
+        .get(url(&prefix))
+        .send()
         .await
         .map_err(|e| e.to_string())?
         .bytes()
@@ -152,7 +389,7 @@ async fn load(
     client: &dyn BlobStorageProvider,
 ) -> Result<(), Box<dyn Error>> {
     let blob_name = pk_to_blob_name(date);
-    let contents = csv::serialize(aircraft.into_iter());
+    let contents = write_parquet(&aircraft)?;
     client.put(&blob_name, contents).await?;
     Ok(())
 }
@@ -163,19 +400,279 @@ pub async fn etl_aircrafts(client: &dyn BlobStorageProvider) -> Result<(), Box<d
     load(aircraft, &now, client).await
 }
 
+/// Where the [`Manifest`] written by [`etl_aircrafts_incremental`] lives. Deliberately outside
+/// [`DATABASE`]'s `date=`-partitioned tree, so [`read_all`]'s directory walk (which parses every
+/// blob name under `DATABASE` as a `date=` hive key) never has to know about it.
+const MANIFEST_BLOB: &str = "aircraft/manifest.json";
+
+/// A cheap, deterministic fingerprint of a byte slice (via [`std::collections::hash_map::DefaultHasher`],
+/// whose keys are fixed rather than randomized per-process), used to detect whether a top-level
+/// ICAO prefix's upstream `.js` changed since the last crawl without diffing its full content.
+fn hash_bytes(data: &[u8]) -> u64 {
+    use std::hash::{Hash, Hasher};
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    data.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Like [`db_current`], but also returns the prefix's raw upstream bytes, so a caller can
+/// fingerprint them (see [`hash_bytes`]) without re-fetching.
+async fn db_current_raw(
+    prefix: String,
+) -> Result<(String, Vec<u8>, HashMap<String, Vec<Option<String>>>), String> {
+    let data = crate::icao_to_trace::http_client()
+        .get(url(&prefix))
+        .send()
+        .await
+        .map_err(|e| e.to_string())?
+        .bytes()
+        .await
+        .map_err(|e| e.to_string())?;
+    let entries = serde_json::from_slice(&data).map_err(|e| e.to_string())?;
+    Ok((prefix, data.to_vec(), entries))
+}
+
+/// Like [`children`], but also returns each descendant's raw upstream bytes (see
+/// [`db_current_raw`]), so a top-level prefix's staleness can be fingerprinted from its whole
+/// subtree rather than just its own page.
+#[async_recursion]
+async fn children_raw<'a: 'async_recursion>(
+    entries: &mut HashMap<String, Vec<Option<String>>>,
+) -> Result<Vec<(String, Vec<u8>, HashMap<String, Vec<Option<String>>>)>, String> {
+    let Some(entries) = entries.remove("children") else {
+        return Ok(Default::default());
+    };
+
+    let mut entries = futures::future::try_join_all(
+        entries.into_iter().map(|x| x.unwrap()).map(db_current_raw),
+    )
+    .await?;
+
+    // recurse over all children
+    let mut _children =
+        futures::future::try_join_all(entries.iter_mut().map(|entry| children_raw(&mut entry.2)))
+            .await?;
+
+    entries.extend(_children.into_iter().flatten());
+    Ok(entries)
+}
+
+/// Records a completed [`etl_aircrafts_incremental`] crawl: a monotonically increasing sync
+/// token and a content hash of each top-level ICAO prefix's whole subtree (its own upstream `.js`
+/// plus every descendant page reached via [`children_raw`]), persisted at [`MANIFEST_BLOB`] so the
+/// next incremental call can tell which prefixes have no change anywhere below them, and skip
+/// re-parsing those into [`Aircraft`] entries.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Manifest {
+    /// The [`Aircraft`] schema version the crawl that produced this manifest was written with.
+    pub version: u32,
+    /// One more than the token of the manifest this crawl replaced; `1` for the first crawl.
+    pub token: u64,
+    prefix_hashes: HashMap<String, u64>,
+}
+
+async fn read_manifest(
+    client: &dyn BlobStorageProvider,
+) -> Result<Option<Manifest>, std::io::Error> {
+    let Some(data) = client.maybe_get(MANIFEST_BLOB).await? else {
+        return Ok(None);
+    };
+    Ok(serde_json::from_slice(&data).ok())
+}
+
+async fn write_manifest(
+    manifest: &Manifest,
+    client: &dyn BlobStorageProvider,
+) -> Result<(), std::io::Error> {
+    let data = serde_json::to_vec(manifest).expect("Manifest always serializes");
+    client.put(MANIFEST_BLOB, data).await
+}
+
+/// What changed in an [`etl_aircrafts_incremental`] crawl relative to the manifest it started
+/// from, plus the fresh [`Manifest`] a caller should keep around to request the next delta.
+pub struct AircraftDelta {
+    pub added: Vec<Aircraft>,
+    pub changed: Vec<Aircraft>,
+    pub removed: Vec<Arc<str>>,
+    pub manifest: Manifest,
+}
+
+/// Like [`etl_aircrafts`], but incremental: reads the [`Manifest`] left by the previous run (a
+/// full crawl, if none is found), fetches every top-level ICAO prefix's subtree but re-parses
+/// only the prefixes whose upstream content hash changed, diffs the refreshed prefixes against
+/// the previous full snapshot to work
+/// out which `icao_number`s were added, changed or removed, writes a new full snapshot (so
+/// [`read`]/[`read_all`] keep seeing one complete, consistent dataset rather than a partial one)
+/// and a fresh manifest with a bumped token, and returns the delta.
+pub async fn etl_aircrafts_incremental(
+    client: &dyn BlobStorageProvider,
+) -> Result<AircraftDelta, Box<dyn Error>> {
+    let prior_manifest = read_manifest(client).await?;
+    let prior_aircraft = match read_all(client).await?.into_iter().max_by_key(|(date, _)| *date) {
+        Some((_, aircraft)) => aircraft,
+        None => Aircrafts::default(),
+    };
+
+    let country_ranges = CountryIcaoRanges::new();
+    let prefixes = (b'A'..=b'F').chain(b'0'..b'9');
+    let prefixes = prefixes.map(|x| std::str::from_utf8(&[x]).unwrap().to_string());
+    let mut fetched = futures::future::try_join_all(prefixes.map(db_current_raw)).await?;
+
+    // fetch every top-level prefix's subtree unconditionally: a top-level page can be just a
+    // listing of further sub-prefixes (see `children`), so a change deep in the tree would
+    // otherwise never surface in the top-level page's own hash.
+    let subtrees =
+        futures::future::try_join_all(fetched.iter_mut().map(|entry| children_raw(&mut entry.2)))
+            .await?;
+
+    let mut prefix_hashes = HashMap::new();
+    let mut refreshed_prefixes = HashSet::new();
+    let mut refreshed = Vec::new();
+    for ((prefix, raw, parsed), mut children) in fetched.into_iter().zip(subtrees) {
+        // fingerprint the whole subtree (this prefix plus every descendant actually fetched),
+        // sorted by prefix so the hash doesn't depend on fetch order.
+        children.sort_by(|a, b| a.0.cmp(&b.0));
+        let hash = hash_bytes(
+            &std::iter::once(raw.as_slice())
+                .chain(children.iter().map(|(_, raw, _)| raw.as_slice()))
+                .collect::<Vec<_>>()
+                .concat(),
+        );
+        let is_stale =
+            prior_manifest.as_ref().and_then(|m| m.prefix_hashes.get(&prefix)) != Some(&hash);
+        prefix_hashes.insert(prefix.clone(), hash);
+        if is_stale {
+            refreshed_prefixes.insert(prefix.to_ascii_lowercase());
+            refreshed.push((prefix, parsed));
+            refreshed.extend(children.into_iter().map(|(prefix, _, parsed)| (prefix, parsed)));
+        }
+    }
+
+    let refreshed_aircraft = refreshed
+        .into_iter()
+        .fold(vec![], |mut acc, (prefix, values)| {
+            let items = values
+                .into_iter()
+                .map(|(k, v)| (format!("{prefix}{k}"), v))
+                .filter_map(|(icao_number, mut data)| {
+                    let tail_number = std::mem::take(&mut data[0])?;
+                    let type_designator = std::mem::take(&mut data[1])?;
+                    let model = std::mem::take(&mut data[3])?;
+                    let country = country_ranges
+                        .country(&icao_number)
+                        .expect("Data from adsb-b to be a valid hex");
+
+                    Some(Aircraft {
+                        icao_number: icao_number.to_ascii_lowercase().into(),
+                        tail_number,
+                        type_designator,
+                        model,
+                        country: country.cloned(),
+                    })
+                });
+            acc.extend(items);
+            acc
+        });
+
+    let mut merged = prior_aircraft.clone();
+    let mut fresh_icao_numbers = HashSet::new();
+    let mut added = Vec::new();
+    let mut changed = Vec::new();
+    for aircraft in refreshed_aircraft {
+        fresh_icao_numbers.insert(aircraft.icao_number.clone());
+        match merged.insert(aircraft.icao_number.clone(), aircraft.clone()) {
+            Some(previous) if previous == aircraft => {}
+            Some(_) => changed.push(aircraft),
+            None => added.push(aircraft),
+        }
+    }
+
+    let removed = prior_aircraft
+        .keys()
+        .filter(|icao| {
+            refreshed_prefixes
+                .iter()
+                .any(|prefix| icao.starts_with(prefix.as_str()))
+                && !fresh_icao_numbers.contains(*icao)
+        })
+        .cloned()
+        .collect::<Vec<_>>();
+    for icao in &removed {
+        merged.remove(icao);
+    }
+
+    let token = prior_manifest.as_ref().map_or(0, |m| m.token) + 1;
+    let manifest = Manifest {
+        version: CURRENT_VERSION,
+        token,
+        prefix_hashes,
+    };
+
+    let now = time::OffsetDateTime::now_utc().date();
+    load(merged.into_values().collect(), &now, client).await?;
+    write_manifest(&manifest, client).await?;
+
+    Ok(AircraftDelta {
+        added,
+        changed,
+        removed,
+        manifest,
+    })
+}
+
 pub async fn read(
     date: Date,
     client: &dyn BlobStorageProvider,
 ) -> Result<Aircrafts, std::io::Error> {
-    let key = pk_to_blob_name(&date);
-    let aircrafts = crate::io::get_csv::<Aircraft>(&key, client).await?;
+    let aircraft = read_filtered(date, None, None, client).await?;
 
-    Ok(aircrafts
+    Ok(aircraft
         .into_iter()
         .map(|x: Aircraft| (x.icao_number.clone(), x))
         .collect())
 }
 
+/// Like [`read`], but pushes an optional column `projection` and equality `filter` (column name,
+/// value) down into the Parquet scan: row groups that can't match `filter` are skipped via their
+/// statistics, and only `projection`'s columns (if given) are materialized. Useful for queries
+/// like "every `type_designator` for `country = "PT"`" that would otherwise scan every column of
+/// every row.
+///
+/// Transparently upgrades `v0` partitions (the unversioned CSV blobs written before
+/// [`CURRENT_VERSION`] existed): `projection`/`filter` are applied to them in memory, since they
+/// predate Parquet row-group statistics.
+pub async fn read_filtered(
+    date: Date,
+    projection: Option<&[&str]>,
+    filter: Option<(&str, &str)>,
+    client: &dyn BlobStorageProvider,
+) -> Result<Vec<Aircraft>, std::io::Error> {
+    let key = pk_to_blob_name(&date);
+    if let Some(data) = client.maybe_get(&key).await? {
+        return read_parquet(data, projection, filter).map_err(std::io::Error::other);
+    }
+
+    let legacy_key = legacy_v0_blob_name(&date);
+    let data = client
+        .maybe_get(&legacy_key)
+        .await?
+        .expect("no aircraft snapshot for this date, at any known schema version");
+    let aircraft = crate::csv::deserialize::<AircraftV0>(&data)
+        .map(upgrade_v0)
+        .filter(|a| match filter {
+            Some((column, value)) => a.column(column) == value,
+            None => true,
+        })
+        .map(|a| a.project(projection))
+        .collect();
+    Ok(aircraft)
+}
+
+/// Reads every known snapshot in full. Every caller needs every column for every aircraft of a
+/// given date (there is no per-date predicate to push down here), so this deliberately calls
+/// [`read`] rather than [`read_filtered`] with a real `filter`/`projection` - the latter earns its
+/// keep in call sites that only need one column or one value, such as a future "list every model
+/// registered in `country = X`" query.
 pub async fn read_all(
     client: &dyn BlobStorageProvider,
 ) -> Result<HashMap<Date, Aircrafts>, std::io::Error> {
@@ -184,7 +681,7 @@ pub async fn read_all(
         .await?
         .into_iter()
         .map(|key| blob_name_to_pk(&key))
-        .collect::<Vec<_>>();
+        .collect::<HashSet<_>>();
 
     let tasks = snapshots
         .into_iter()
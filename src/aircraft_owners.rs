@@ -2,6 +2,8 @@ use std::{collections::HashMap, error::Error};
 
 use serde::{Deserialize, Serialize};
 
+use crate::fs;
+
 pub type AircraftOwners = HashMap<String, AircraftOwner>;
 
 /// The in-memory representation of an aircraft owner
@@ -13,11 +15,22 @@ pub struct AircraftOwner {
     pub date: String,
 }
 
-/// Loads owners from `src/owners.csv` into memory has a map `tail_number: KnownOwner`.
+static CACHE_PATH: &str = "database/aircraft_owners";
+const CACHE_VERSION: u8 = 1;
+
+/// Loads owners from `src/owners.csv` into memory has a map `tail_number: KnownOwner`,
+/// caching the parsed result as a versioned binary blob at `database/aircraft_owners-v1.bin`
+/// so that subsequent runs skip re-parsing the CSV.
+/// Pass `refresh = true` to force a re-parse, e.g. after `src/owners.csv` changed.
 /// # Error
 /// Errors if the file cannot be read
-pub fn load_aircraft_owners() -> Result<AircraftOwners, Box<dyn Error>> {
-    super::csv::load("src/owners.csv", |a: AircraftOwner| {
-        (a.tail_number.clone(), a)
+pub fn load_aircraft_owners(refresh: bool) -> Result<AircraftOwners, Box<dyn Error>> {
+    let data = std::fs::read("src/owners.csv")?;
+
+    fs::cached_parse(CACHE_PATH, CACHE_VERSION, &data, refresh, |data| {
+        let owners = super::csv::deserialize::<AircraftOwner>(data)
+            .map(|a| (a.tail_number.clone(), a))
+            .collect();
+        Ok::<_, Box<dyn Error>>(owners)
     })
 }
@@ -1,16 +1,90 @@
+use std::collections::HashMap;
 use std::io::Cursor;
+use std::sync::Arc;
 
 use crate::fs;
 
-#[derive(Debug, serde::Deserialize, Clone)]
+/// Mean Earth radius (meters), used by [`haversine_km`] below.
+const EARTH_RAD_METERS: f64 = 6371e3;
+
+#[derive(Debug, serde::Deserialize)]
 pub struct Airport {
+    pub id: usize,
+    pub icao_code: Option<String>,
+    pub iata_code: Option<String>,
     pub name: String,
+    pub municipality: Option<String>,
+    pub iso_country: Option<String>,
     pub latitude_deg: f64,
     pub longitude_deg: f64,
     #[serde(rename = "type")]
     pub type_: String,
 }
 
+/// Great-circle distance between two `(latitude_deg, longitude_deg)` points, in km.
+fn haversine_km(from: (f64, f64), to: (f64, f64)) -> f64 {
+    let (lat1, lon1) = (from.0.to_radians(), from.1.to_radians());
+    let (lat2, lon2) = (to.0.to_radians(), to.1.to_radians());
+    let d_lat = lat2 - lat1;
+    let d_lon = lon2 - lon1;
+    let a = (d_lat / 2.0).sin().powi(2) + lat1.cos() * lat2.cos() * (d_lon / 2.0).sin().powi(2);
+    EARTH_RAD_METERS * 2.0 * a.sqrt().asin() / 1000.0
+}
+
+/// A leg endpoint farther than this from any known airport is treated as mid-air
+/// and left unlabelled rather than snapped to a misleadingly-distant airport.
+pub const NEAREST_AIRPORT_MAX_KM: f64 = 5.0;
+
+/// An indexed airport reference table: a `HashMap` for id lookups and a flat `Vec` for
+/// nearest-neighbour scans over the same `Arc<Airport>`s.
+pub struct Airports {
+    by_id: HashMap<usize, Arc<Airport>>,
+    all: Vec<Arc<Airport>>,
+}
+
+impl Airports {
+    fn new(airports: Vec<Airport>) -> Self {
+        let all: Vec<Arc<Airport>> = airports.into_iter().map(Arc::new).collect();
+        let by_id = all.iter().map(|airport| (airport.id, airport.clone())).collect();
+        Self { by_id, all }
+    }
+
+    /// Returns the [`Airport`] with the given `id`, if any.
+    pub fn get(&self, id: usize) -> Option<&Arc<Airport>> {
+        self.by_id.get(&id)
+    }
+
+    /// Returns the closest [`Airport`] to `pos`, or `None` when the nearest one is farther
+    /// than `max_distance_km` away (e.g. a mid-air position between airports).
+    pub fn nearest(&self, pos: (f64, f64), max_distance_km: f64) -> Option<Arc<Airport>> {
+        self.all
+            .iter()
+            .map(|airport| {
+                (
+                    airport,
+                    haversine_km(pos, (airport.latitude_deg, airport.longitude_deg)),
+                )
+            })
+            .min_by(|(_, a), (_, b)| a.total_cmp(b))
+            .filter(|(_, distance)| *distance <= max_distance_km)
+            .map(|(airport, _)| airport.clone())
+    }
+
+    /// Labels `pos` with the nearest airport's ICAO code (falling back to its name when no code
+    /// is on file) within [`NEAREST_AIRPORT_MAX_KM`], or an empty string when nothing is close
+    /// enough.
+    pub fn label(&self, pos: (f64, f64)) -> String {
+        self.nearest(pos, NEAREST_AIRPORT_MAX_KM)
+            .map(|airport| {
+                airport
+                    .icao_code
+                    .clone()
+                    .unwrap_or_else(|| airport.name.clone())
+            })
+            .unwrap_or_default()
+    }
+}
+
 async fn airports() -> Result<Vec<u8>, reqwest::Error> {
     let url = "https://raw.githubusercontent.com/davidmegginson/ourairports-data/main/airports.csv";
     Ok(reqwest::get(url).await?.bytes().await.map(|x| x.into())?)
@@ -19,12 +93,13 @@ async fn airports() -> Result<Vec<u8>, reqwest::Error> {
 /// Returns a list of airports
 /// # Implementation
 /// Data is cached on disk the first time it is executed
-pub async fn airports_cached() -> Result<Vec<Airport>, Box<dyn std::error::Error>> {
+pub async fn airports_cached() -> Result<Airports, Box<dyn std::error::Error>> {
     let data = fs::cached(
         "database/airports.csv",
         airports(),
         &fs::LocalDisk,
         fs::CacheAction::ReadFetchWrite,
+        0,
     )
     .await?;
 
@@ -39,20 +114,5 @@ pub async fn airports_cached() -> Result<Vec<Airport>, Box<dyn std::error::Error
         .filter(|airport| airport.type_ == "medium_airport" || airport.type_ == "large_airport")
         .collect::<Vec<_>>();
 
-    Ok(data)
-}
-
-/// Returns the closest [`Airport`] from `pos`.
-pub fn closest(pos: (f64, f64), airports: &[Airport]) -> Airport {
-    airports
-        .iter()
-        .fold((airports[0].clone(), f64::MAX), |mut acc, airport| {
-            let distance = super::distance(pos, (airport.latitude_deg, airport.longitude_deg));
-            if distance < acc.1 {
-                acc.0 = airport.clone();
-                acc.1 = distance;
-            }
-            acc
-        })
-        .0
+    Ok(Airports::new(data))
 }
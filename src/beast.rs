@@ -0,0 +1,455 @@
+//! Ingests the Beast binary protocol (as emitted by `dump1090`/`readsb` feeders) and decodes
+//! Mode-S/ADS-B frames into the crate's [`Position`] type, so a live receiver can be used as an
+//! alternative to the cached adsbexchange history.
+//!
+//! Airborne positions are CPR-encoded and ambiguous from a single frame: [`Decoder`] buffers
+//! the most recent even- and odd-format frame per ICAO address and, once both are present and
+//! close enough in time, resolves the absolute position with [`global_decode`]. When only one
+//! frame is available (or the pair straddles a latitude-zone boundary), it falls back to
+//! [`local_decode`] against the last known position for that aircraft.
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use tokio::io::AsyncReadExt;
+use tokio::net::TcpStream;
+
+use crate::Position;
+
+const ESCAPE: u8 = 0x1a;
+
+/// The type of a Beast frame, given by the byte following the `0x1a` escape.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FrameType {
+    ModeAc,
+    ModeSShort,
+    ModeSLong,
+}
+
+impl FrameType {
+    fn from_byte(byte: u8) -> Option<Self> {
+        match byte {
+            b'1' => Some(Self::ModeAc),
+            b'2' => Some(Self::ModeSShort),
+            b'3' => Some(Self::ModeSLong),
+            _ => None,
+        }
+    }
+
+    /// Payload length in bytes, excluding the 6-byte MLAT timestamp and 1-byte signal level.
+    fn payload_len(&self) -> usize {
+        match self {
+            Self::ModeAc => 2,
+            Self::ModeSShort => 7,
+            Self::ModeSLong => 14,
+        }
+    }
+}
+
+/// A single decoded Beast frame.
+#[derive(Debug, Clone)]
+pub(crate) struct BeastFrame {
+    pub mlat_timestamp: u64,
+    pub signal_level: u8,
+    pub payload: Vec<u8>,
+}
+
+/// Splits a raw Beast byte stream into frames, un-escaping doubled `0x1a` bytes.
+/// Stops at the first incomplete trailing frame (left for the next read).
+pub(crate) fn parse_frames(stream: &[u8]) -> Vec<BeastFrame> {
+    let mut frames = Vec::new();
+    let mut i = 0;
+    while i < stream.len() {
+        if stream[i] != ESCAPE {
+            i += 1;
+            continue;
+        }
+        let Some(&type_byte) = stream.get(i + 1) else {
+            break;
+        };
+        let Some(frame_type) = FrameType::from_byte(type_byte) else {
+            i += 1;
+            continue;
+        };
+
+        // un-escape the body: 6 bytes MLAT + 1 byte signal + payload, with any 0x1a doubled
+        let needed = 6 + 1 + frame_type.payload_len();
+        let mut body = Vec::with_capacity(needed);
+        let mut j = i + 2;
+        while body.len() < needed && j < stream.len() {
+            if stream[j] == ESCAPE {
+                // a doubled escape is a literal 0x1a; a lone one starts the next frame
+                if stream.get(j + 1) == Some(&ESCAPE) {
+                    body.push(ESCAPE);
+                    j += 2;
+                    continue;
+                } else {
+                    break;
+                }
+            }
+            body.push(stream[j]);
+            j += 1;
+        }
+        if body.len() < needed {
+            break;
+        }
+
+        let mlat_timestamp = body[..6]
+            .iter()
+            .fold(0u64, |acc, &byte| (acc << 8) | byte as u64);
+        let signal_level = body[6];
+        let payload = body[7..7 + frame_type.payload_len()].to_vec();
+
+        frames.push(BeastFrame {
+            mlat_timestamp,
+            signal_level,
+            payload,
+        });
+        i = j;
+    }
+    frames
+}
+
+fn icao_from_payload(payload: &[u8]) -> Option<Arc<str>> {
+    // bytes 1..4 of a Mode-S extended squitter are the ICAO address
+    (payload.len() >= 4).then(|| format!("{:02x}{:02x}{:02x}", payload[1], payload[2], payload[3]).into())
+}
+
+/// A single even/odd CPR-encoded airborne position frame, buffered until its pair arrives.
+#[derive(Debug, Clone, Copy)]
+struct CprFrame {
+    odd: bool,
+    lat_cpr: u32,
+    lon_cpr: u32,
+    altitude: Option<f64>,
+    received_at: time::OffsetDateTime,
+}
+
+fn decode_airborne_position(payload: &[u8]) -> Option<CprFrame> {
+    // extended squitter (DF17/18): the top 5 bits of byte 0 are the downlink format,
+    // byte 4 is the type code; 9..18 are airborne-position types.
+    let df = payload[0] >> 3;
+    if df != 17 && df != 18 {
+        return None;
+    }
+    if payload.len() < 11 {
+        return None;
+    }
+    // airborne position with barometric altitude (9..=18) or with GNSS height (20..=22);
+    // both share the same ME bit layout, only the altitude source differs
+    let type_code = payload[4] >> 3;
+    if !(9..=18).contains(&type_code) && !(20..=22).contains(&type_code) {
+        return None;
+    }
+    let me = &payload[4..11];
+    let odd = (me[2] & 0x04) != 0;
+    let lat_cpr = (((me[2] as u32 & 0x03) << 15) | ((me[3] as u32) << 7) | ((me[4] as u32) >> 1)) & 0x1ffff;
+    let lon_cpr = (((me[4] as u32 & 0x01) << 16) | ((me[5] as u32) << 8) | (me[6] as u32)) & 0x1ffff;
+
+    // altitude: 12-bit field split across bytes 1-2
+    let alt_bits = (((me[1] as u16) << 4) | ((me[2] as u16) >> 4)) & 0x0fff;
+    let altitude = decode_altitude(alt_bits);
+
+    Some(CprFrame {
+        odd,
+        lat_cpr,
+        lon_cpr,
+        altitude,
+        received_at: time::OffsetDateTime::now_utc(),
+    })
+}
+
+/// Decodes a 12-bit Mode-S altitude field, honoring the Q-bit (bit 4) that selects its encoding:
+/// when set, the remaining 11 bits are a 25ft-resolution altitude offset from -1000ft; when
+/// unset, the field is legacy Gillham (Mode-C) encoded in 100ft increments, which this decoder
+/// doesn't support (rare in modern DF17/18 transponders), so it returns `None`.
+fn decode_altitude(alt_bits: u16) -> Option<f64> {
+    if alt_bits == 0 {
+        return None;
+    }
+    let q = (alt_bits & 0x10) != 0;
+    if !q {
+        return None;
+    }
+    let n = ((alt_bits & 0x0fe0) >> 1) | (alt_bits & 0x000f);
+    Some(n as f64 * 25.0 - 1000.0)
+}
+
+/// Locally decodes a CPR-encoded lat/lon against a known reference position, as described in
+/// the ADS-B specification's local decoding procedure. Used as a fallback when only one of the
+/// even/odd frames has been observed, or when a pair straddles a latitude-zone boundary and
+/// [`global_decode`] refuses to resolve it.
+fn local_decode(frame: &CprFrame, reference: (f64, f64)) -> (f64, f64) {
+    let d_lat = if frame.odd { 360.0 / 59.0 } else { 360.0 / 60.0 };
+    let lat_cpr = frame.lat_cpr as f64 / 131072.0;
+    let lon_cpr = frame.lon_cpr as f64 / 131072.0;
+
+    let j = (reference.0 / d_lat).floor() + (0.5 + reference.0.rem_euclid(d_lat) / d_lat - lat_cpr).floor();
+    let lat = d_lat * (j + lat_cpr);
+
+    let d_lon = 360.0 / (cpr_nl(lat) - frame.odd as i32).max(1) as f64;
+    let m = (reference.1 / d_lon).floor() + (0.5 + reference.1.rem_euclid(d_lon) / d_lon - lon_cpr).floor();
+    let lon = d_lon * (m + lon_cpr);
+
+    (lat, lon)
+}
+
+/// Number of longitude zones at a given latitude, per the CPR spec (`NZ` = 15 geographic
+/// latitude zones per pole).
+fn cpr_nl(lat: f64) -> i32 {
+    if lat == 0.0 {
+        return 59;
+    }
+    if lat.abs() >= 87.0 {
+        return 1;
+    }
+    const NZ: f64 = 15.0;
+    let a = 1.0 - (1.0 - (std::f64::consts::PI / (2.0 * NZ)).cos()) / lat.to_radians().cos().powi(2);
+    (2.0 * std::f64::consts::PI / a.acos()).floor() as i32
+}
+
+/// Globally decodes an absolute lat/lon from one even- and one odd-format CPR frame, per the
+/// ADS-B specification's global decoding procedure. Returns `None` when the two frames
+/// straddle a latitude-zone boundary, in which case the position is ambiguous and the caller
+/// should fall back to [`local_decode`].
+fn global_decode(even: &CprFrame, odd: &CprFrame) -> Option<(f64, f64)> {
+    let lat_cpr_even = even.lat_cpr as f64 / 131072.0;
+    let lat_cpr_odd = odd.lat_cpr as f64 / 131072.0;
+
+    let d_lat_even = 360.0 / 60.0;
+    let d_lat_odd = 360.0 / 59.0;
+
+    let j = (59.0 * lat_cpr_even - 60.0 * lat_cpr_odd + 0.5).floor();
+
+    let wrap = |lat: f64| if lat >= 270.0 { lat - 360.0 } else { lat };
+    let lat_even = wrap(d_lat_even * (j.rem_euclid(60.0) + lat_cpr_even));
+    let lat_odd = wrap(d_lat_odd * (j.rem_euclid(59.0) + lat_cpr_odd));
+
+    if cpr_nl(lat_even) != cpr_nl(lat_odd) {
+        return None;
+    }
+
+    let latest_is_odd = odd.received_at >= even.received_at;
+    let lat = if latest_is_odd { lat_odd } else { lat_even };
+
+    let nl = cpr_nl(lat);
+    let ni = (nl - latest_is_odd as i32).max(1);
+
+    let lon_cpr_even = even.lon_cpr as f64 / 131072.0;
+    let lon_cpr_odd = odd.lon_cpr as f64 / 131072.0;
+    let lon_cpr = if latest_is_odd { lon_cpr_odd } else { lon_cpr_even };
+
+    let m = (lon_cpr_even * (nl - 1) as f64 - lon_cpr_odd * nl as f64 + 0.5).floor();
+    let d_lon = 360.0 / ni as f64;
+    let lon = d_lon * (m.rem_euclid(ni as f64) + lon_cpr);
+    let lon = if lon >= 180.0 { lon - 360.0 } else { lon };
+
+    Some((lat, lon))
+}
+
+/// Pairs older than this, relative to each other, are not used for [`global_decode`]: the
+/// aircraft may have moved enough between them to make the pair unreliable.
+const CPR_PAIR_MAX_AGE_SECONDS: i64 = 10;
+
+/// Per-ICAO decoder state: the most recent even/odd CPR frames, used to resolve the
+/// globally-unambiguous position once a matching pair is available.
+#[derive(Default)]
+struct Decoder {
+    even: Option<CprFrame>,
+    odd: Option<CprFrame>,
+    last_position: Option<(f64, f64)>,
+}
+
+impl Decoder {
+    fn push(&mut self, frame: CprFrame) -> Option<(time::OffsetDateTime, f64, f64, Option<f64>)> {
+        let altitude = frame.altitude;
+        let received_at = frame.received_at;
+        if frame.odd {
+            self.odd = Some(frame);
+        } else {
+            self.even = Some(frame);
+        }
+
+        let position = match (&self.even, &self.odd) {
+            (Some(even), Some(odd))
+                if (even.received_at - odd.received_at).whole_seconds().abs() <= CPR_PAIR_MAX_AGE_SECONDS =>
+            {
+                global_decode(even, odd)
+            }
+            _ => None,
+        }
+        .or_else(|| self.last_position.map(|reference| local_decode(&frame, reference)));
+
+        // a garbled frame or a bad local reference can produce nonsense coordinates; drop them
+        // rather than feed an out-of-range position into the leg-identification logic
+        let position = position.filter(|(lat, lon)| {
+            (-90.0..=90.0).contains(lat) && (-180.0..=180.0).contains(lon)
+        })?;
+        self.last_position = Some(position);
+        Some((received_at, position.0, position.1, altitude))
+    }
+}
+
+/// Connects to a `dump1090`/`readsb`-style raw Beast TCP feed (e.g. `127.0.0.1:30005`) and
+/// returns the decoded per-ICAO [`Position`]s observed for `max_frames` frames.
+/// # Implementation
+/// This is the live-receiver counterpart to [`crate::icao_to_trace::positions`]: instead of
+/// replaying cached history, it reads frames as they arrive on the wire.
+pub async fn read_live_positions(
+    receiver_addr: &str,
+    max_frames: usize,
+) -> Result<HashMap<Arc<str>, Vec<Position>>, std::io::Error> {
+    let mut stream = TcpStream::connect(receiver_addr).await?;
+    let mut buf = vec![0u8; 64 * 1024];
+    let mut decoders = HashMap::<Arc<str>, Decoder>::new();
+    let mut positions = HashMap::<Arc<str>, Vec<Position>>::new();
+    let mut seen = 0;
+
+    while seen < max_frames {
+        let n = stream.read(&mut buf).await?;
+        if n == 0 {
+            break;
+        }
+        for frame in parse_frames(&buf[..n]) {
+            let Some(icao) = icao_from_payload(&frame.payload) else {
+                continue;
+            };
+            let Some(cpr) = decode_airborne_position(&frame.payload) else {
+                continue;
+            };
+            seen += 1;
+            let decoder = decoders.entry(icao.clone()).or_default();
+            if let Some((datetime, latitude, longitude, altitude)) = decoder.push(cpr) {
+                positions.entry(icao).or_default().push(Position {
+                    datetime,
+                    latitude,
+                    longitude,
+                    altitude,
+                });
+            }
+        }
+    }
+    Ok(positions)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn roundtrip_escaping() {
+        // a minimal Mode-S short frame (7-byte payload) with a literal 0x1a inside it
+        let mut raw = vec![ESCAPE, b'2'];
+        raw.extend([0, 0, 0, 0, 0, 0]); // MLAT timestamp
+        raw.push(10); // signal level
+        raw.extend([1, 2, ESCAPE, ESCAPE, 3, 4, 5, 6]); // payload, with an escaped 0x1a
+
+        let frames = parse_frames(&raw);
+        assert_eq!(frames.len(), 1);
+        assert_eq!(frames[0].payload, vec![1, 2, ESCAPE, 3, 4, 5, 6]);
+    }
+
+    #[test]
+    fn icao_extraction() {
+        let payload = [0x8d, 0x45, 0x86, 0x0d, 0, 0, 0];
+        assert_eq!(icao_from_payload(&payload), Some("45860d".into()));
+    }
+
+    #[test]
+    fn altitude_decoding_honors_q_bit() {
+        // Q-bit set (0x10): the other 11 bits are a 25ft-resolution offset from -1000ft,
+        // here encoding 38000ft
+        assert_eq!(decode_altitude(0xc38), Some(38000.0));
+        // empty field means "altitude unavailable"
+        assert_eq!(decode_altitude(0), None);
+        // Q-bit unset: legacy Gillham encoding, not decoded
+        assert_eq!(decode_altitude(0x0c9), None);
+    }
+
+    #[test]
+    fn global_decode_worked_example() {
+        // classic worked example from the ADS-B decoding guide: an aircraft near
+        // Cebu (Philippines) reporting an even and an odd frame a second apart
+        let now = time::OffsetDateTime::now_utc();
+        let even = CprFrame {
+            odd: false,
+            lat_cpr: 92095,
+            lon_cpr: 39846,
+            altitude: None,
+            received_at: now,
+        };
+        let odd = CprFrame {
+            odd: true,
+            lat_cpr: 88385,
+            lon_cpr: 125818,
+            altitude: None,
+            received_at: now + time::Duration::seconds(1),
+        };
+
+        let (lat, lon) = global_decode(&even, &odd).unwrap();
+        assert!((lat - 10.21621).abs() < 1e-3);
+        assert!((lon - 123.88913).abs() < 1e-3);
+    }
+
+    #[test]
+    fn decoder_prefers_global_decode_over_local() {
+        let now = time::OffsetDateTime::now_utc();
+        let mut decoder = Decoder::default();
+
+        let even = CprFrame {
+            odd: false,
+            lat_cpr: 92095,
+            lon_cpr: 39846,
+            altitude: Some(10000.0),
+            received_at: now,
+        };
+        let odd = CprFrame {
+            odd: true,
+            lat_cpr: 88385,
+            lon_cpr: 125818,
+            altitude: Some(10000.0),
+            received_at: now + time::Duration::seconds(1),
+        };
+
+        assert!(decoder.push(even).is_none());
+        let (_, lat, lon, _) = decoder.push(odd).unwrap();
+        assert!((lat - 10.21621).abs() < 1e-3);
+        assert!((lon - 123.88913).abs() < 1e-3);
+    }
+
+    #[test]
+    fn decoder_rejects_out_of_range_local_decode() {
+        let now = time::OffsetDateTime::now_utc();
+        let mut decoder = Decoder::default();
+        // a reference next to the antimeridian can resolve to a longitude past 180 degrees
+        decoder.last_position = Some((52.25720, 179.9));
+
+        let even = CprFrame {
+            odd: false,
+            lat_cpr: 92095,
+            lon_cpr: 39846,
+            altitude: Some(10000.0),
+            received_at: now,
+        };
+
+        assert!(decoder.push(even).is_none());
+    }
+
+    #[test]
+    fn decoder_falls_back_to_local_decode_without_a_pair() {
+        let now = time::OffsetDateTime::now_utc();
+        let mut decoder = Decoder::default();
+        decoder.last_position = Some((52.25720, 3.91937));
+
+        let even = CprFrame {
+            odd: false,
+            lat_cpr: 92095,
+            lon_cpr: 39846,
+            altitude: Some(10000.0),
+            received_at: now,
+        };
+
+        let (_, lat, lon, _) = decoder.push(even).unwrap();
+        assert!((lat - 52.25720).abs() < 1.0);
+        assert!((lon - 3.91937).abs() < 1.0);
+    }
+}
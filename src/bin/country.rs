@@ -7,9 +7,9 @@ use simple_logger::SimpleLogger;
 use time::macros::date;
 
 use flights::{
-    aircraft, airports_cached, closest, emissions, leg_co2e_kg, leg_per_person,
-    load_private_jet_models, AircraftModels, BlobStorageProvider, Class, Fact, Leg, LocalDisk,
-    Position,
+    aircraft, airports_cached, emissions, leg_co2e_kg_from_profile, leg_per_person,
+    load_private_jet_models, AircraftModels, BlobStorageProvider, Class, Fact, Gtfs, Leg,
+    LocalDisk, Position, MAX_STOP_DISTANCE_KM, RAIL_KG_CO2E_PER_PASSENGER_KM,
 };
 use time::Date;
 
@@ -29,7 +29,7 @@ fn render(context: &Context) -> Result<(), Box<dyn Error>> {
     Ok(())
 }
 
-#[derive(serde::Serialize)]
+#[derive(serde::Serialize, serde::Deserialize)]
 struct LegOut {
     tail_number: String,
     model: String,
@@ -42,8 +42,11 @@ struct LegOut {
     to_airport: String,
     to_lat: f64,
     to_lon: f64,
-    commercial_emissions_kg: usize,
-    emissions_kg: usize,
+    commercial_emissions_kg: f64,
+    emissions_kg: f64,
+    /// Name of the `--region-file` region this leg matched, empty when none was configured or
+    /// matched
+    region: String,
 }
 
 #[derive(serde::Serialize)]
@@ -73,6 +76,15 @@ pub struct Context {
 enum Backend {
     Disk,
     Remote,
+    /// Build legs from a live ADS-B receiver (see `--receiver-addr`) instead of cached history
+    Live,
+}
+
+#[derive(clap::ValueEnum, Debug, Clone, Copy, Default)]
+enum Format {
+    #[default]
+    Csv,
+    Ndjson,
 }
 
 fn parse_date(arg: &str) -> Result<time::Date, time::error::Parse> {
@@ -90,23 +102,52 @@ enum Country {
     Germany,
 }
 
-#[derive(clap::ValueEnum, Debug, Clone, Copy)]
-enum Location {
-    Davos,
+/// A user-defined rectangular region to restrict a search geographically, optionally
+/// bounded by altitude (in feet). Loaded, as one of possibly several, from a `--region-file`
+/// JSON document, e.g.
+/// `[{ "name": "Davos airport (LSZR)", "upper_lat": 47.490, "upper_lon": 9.568, "bottom_lat": 47.482, "bottom_lon": 9.538 }]`
+#[derive(Debug, Clone, serde::Deserialize)]
+struct Region {
+    name: String,
+    upper_lat: f64,
+    upper_lon: f64,
+    bottom_lat: f64,
+    bottom_lon: f64,
+    /// Minimum altitude (feet) a position must be at to match; `None` means no floor
+    floor: Option<f64>,
+    /// Maximum altitude (feet) a position must be at to match; `None` means no ceiling
+    ceiling: Option<f64>,
 }
 
-impl Location {
-    fn name(&self) -> &'static str {
-        match self {
-            Self::Davos => "Davos airport (LSZR)",
+impl Region {
+    /// Converts this named, JSON-loaded region into the crate-level [`flights::Region`] that
+    /// actually implements the bounding-box/altitude-band check.
+    fn as_region(&self) -> flights::Region {
+        flights::Region {
+            upper_lat: self.upper_lat,
+            lower_lat: self.bottom_lat,
+            upper_lon: self.upper_lon,
+            lower_lon: self.bottom_lon,
+            floor: self.floor,
+            ceiling: self.ceiling,
         }
     }
+}
 
-    fn region(&self) -> [[f64; 2]; 2] {
-        match self {
-            Self::Davos => [[47.482, 9.538], [47.490, 9.568]],
-        }
-    }
+/// Loads the named [`Region`]s from a JSON file passed via `--region-file`
+fn load_regions(path: &std::path::Path) -> Result<Vec<Region>, Box<dyn Error>> {
+    let data = std::fs::read(path)?;
+    Ok(serde_json::from_slice(&data)?)
+}
+
+/// Loads [`LegOut`]s previously written by `--format ndjson`, so a previous run can be
+/// re-aggregated or merged with another country's without re-fetching positions from the
+/// backend.
+/// # Errors
+/// Errors if the file cannot be read or a line fails to parse, with the offending line number
+fn load_legs_ndjson(path: &std::path::Path) -> Result<Vec<LegOut>, Box<dyn Error>> {
+    let data = std::fs::read(path)?;
+    flights::csv::deserialize_ndjson(&data).collect()
 }
 
 impl Country {
@@ -203,37 +244,78 @@ struct Cli {
     #[arg(long, value_parser = parse_date)]
     to: Option<time::Date>,
 
-    /// Optional location to restrict the search geographically. Currently only
+    /// Optional path to a JSON file with a list of named [`Region`]s to restrict the search
+    /// geographically and, optionally, by altitude band. A leg is kept when any position
+    /// matches any region
+    #[arg(long)]
+    region_file: Option<std::path::PathBuf>,
+
+    /// The format used to write `data.{csv,ndjson}`
+    #[arg(long, value_enum, default_value_t=Format::Csv)]
+    format: Format,
+
+    /// Optional path to a `data.ndjson` file from a previous run (possibly for a different
+    /// country), whose legs are merged into this run's output file without being re-fetched
+    /// or counted in this run's statistics
+    #[arg(long)]
+    merge_ndjson: Option<std::path::PathBuf>,
+
+    /// Address (e.g. `127.0.0.1:30005`) of a raw Beast ADS-B feed, required when
+    /// `--backend live` is used
+    #[arg(long)]
+    receiver_addr: Option<String>,
+
+    /// Optional path to a GTFS feed (zip archive) used to estimate a real rail alternative
+    /// for legs under 300km, instead of a flat multiplier
+    #[arg(long)]
+    gtfs_file: Option<std::path::PathBuf>,
+
+    /// Force a rebuild of the local private-jet-models binary cache instead of reusing it
     #[arg(long)]
-    location: Option<Location>,
+    refresh: bool,
 }
 
-pub fn in_box(position: &Position, region: [[f64; 2]; 2]) -> bool {
-    return (position.latitude() >= region[0][0] && position.latitude() < region[1][0])
-        && (position.longitude() >= region[0][1] && position.longitude() < region[1][1]);
+/// Returns the name of the first of `regions` whose box (and altitude band) contains any
+/// position of `leg`, or `None` when `regions` is empty or none of them match.
+fn leg_region<'a>(leg: &Leg, regions: &'a [Region]) -> Option<&'a str> {
+    regions
+        .iter()
+        .find(|region| {
+            let region = region.as_region();
+            leg.positions().iter().any(|p| region.contains(p))
+        })
+        .map(|region| region.name.as_str())
 }
 
 async fn legs(
     from: Date,
     to: Date,
     icao_number: &str,
-    location: Option<Location>,
+    regions: &[Region],
+    receiver_addr: Option<&str>,
     client: &dyn BlobStorageProvider,
 ) -> Result<Vec<Leg>, Box<dyn Error>> {
-    let positions = flights::aircraft_positions(from, to, icao_number, client).await?;
+    let positions = if let Some(receiver_addr) = receiver_addr {
+        flights::beast::read_live_positions(receiver_addr, 10_000)
+            .await?
+            .remove(icao_number)
+            .unwrap_or_default()
+    } else {
+        flights::aircraft_positions(from, to, icao_number, client).await?
+    };
 
     log::info!("Computing legs {}", icao_number);
     let legs = flights::legs(positions.into_iter());
 
-    // filter by location
-    if let Some(location) = location {
-        let region = location.region();
+    // no regions configured => keep every leg; otherwise keep a leg when any of its positions
+    // lies inside a named region (within its altitude band)
+    if regions.is_empty() {
+        Ok(legs.into_iter().collect())
+    } else {
         Ok(legs
             .into_iter()
-            .filter(|leg| leg.positions().iter().any(|p| in_box(p, region)))
+            .filter(|leg| leg_region(leg, regions).is_some())
             .collect())
-    } else {
-        Ok(legs)
     }
 }
 
@@ -247,7 +329,8 @@ fn private_emissions(
             legs.iter()
                 .filter(filter)
                 .map(|leg| {
-                    leg_co2e_kg(models.get(model).expect(model).gph as f64, leg.duration()) / 1000.0
+                    leg_co2e_kg_from_profile(models.get(model).expect(model).gph as f64, leg)
+                        / 1000.0
                 })
                 .sum::<f64>()
         })
@@ -279,7 +362,7 @@ async fn main() -> Result<(), Box<dyn Error>> {
 
     // initialize client
     let client = match (cli.backend, cli.access_key, cli.secret_access_key) {
-        (Backend::Disk, _, _) => None,
+        (Backend::Disk, _, _) | (Backend::Live, _, _) => None,
         (_, Some(access_key), Some(secret_access_key)) => {
             Some(flights::fs_s3::client(access_key, secret_access_key).await)
         }
@@ -292,7 +375,7 @@ async fn main() -> Result<(), Box<dyn Error>> {
 
     // load datasets to memory
     let aircrafts = aircraft::read(date!(2023 - 11 - 06), client).await?;
-    let models = load_private_jet_models()?;
+    let models = load_private_jet_models(cli.refresh)?;
     let airports = airports_cached().await?;
 
     let private_jets = aircrafts
@@ -310,8 +393,28 @@ async fn main() -> Result<(), Box<dyn Error>> {
     let from_date = from.to_string();
     let to_date = to.to_string();
 
+    let regions = cli
+        .region_file
+        .as_deref()
+        .map(load_regions)
+        .transpose()?
+        .unwrap_or_default();
+
+    let gtfs = cli
+        .gtfs_file
+        .as_deref()
+        .map(|path| Gtfs::from_zip(&std::fs::read(path)?))
+        .transpose()?;
+
     let legs = private_jets.iter().map(|(_, aircraft)| async {
-        legs(from, to, &aircraft.icao_number, cli.location, client)
+        legs(
+            from,
+            to,
+            &aircraft.icao_number,
+            &regions,
+            cli.receiver_addr.as_deref(),
+            client,
+        )
             .await
             .map(|legs| ((aircraft.tail_number.clone(), aircraft.model.clone()), legs))
     });
@@ -322,33 +425,39 @@ async fn main() -> Result<(), Box<dyn Error>> {
         .try_collect::<HashMap<_, _>>()
         .await?;
 
-    let mut wtr = csv::Writer::from_writer(vec![]);
-    for ((tail_number, model), legs) in legs.iter() {
-        for leg in legs {
-            wtr.serialize(LegOut {
-                tail_number: tail_number.to_string(),
-                model: model.to_string(),
-                start: leg.from().datetime().to_string(),
-                end: leg.to().datetime().to_string(),
-                duration: leg.duration().to_string(),
-                from_airport: closest(leg.from().pos(), &airports).name,
-                to_airport: closest(leg.to().pos(), &airports).name,
-                from_lat: leg.from().latitude(),
-                from_lon: leg.from().longitude(),
-                to_lat: leg.to().latitude(),
-                to_lon: leg.to().longitude(),
-                commercial_emissions_kg: emissions(leg.from().pos(), leg.to().pos(), Class::First)
-                    as usize,
-                emissions_kg: leg_co2e_kg(
-                    models.get(model).expect(model).gph as f64,
-                    leg.duration(),
-                ) as usize,
-            })
-            .unwrap()
+    let legs_out = legs.iter().flat_map(|((tail_number, model), legs)| {
+        legs.iter().map(|leg| LegOut {
+            tail_number: tail_number.to_string(),
+            model: model.to_string(),
+            start: leg.from().datetime().to_string(),
+            end: leg.to().datetime().to_string(),
+            duration: leg.duration().to_string(),
+            from_airport: airports.label(leg.from().pos()),
+            to_airport: airports.label(leg.to().pos()),
+            from_lat: leg.from().latitude(),
+            from_lon: leg.from().longitude(),
+            to_lat: leg.to().latitude(),
+            to_lon: leg.to().longitude(),
+            commercial_emissions_kg: emissions(leg.from().pos(), leg.to().pos(), Class::First),
+            emissions_kg: leg_co2e_kg_from_profile(models.get(model).expect(model).gph as f64, leg),
+            region: leg_region(leg, &regions).unwrap_or_default().to_string(),
+        })
+    });
+
+    let merged_legs = cli
+        .merge_ndjson
+        .as_deref()
+        .map(load_legs_ndjson)
+        .transpose()?
+        .unwrap_or_default();
+    let legs_out = legs_out.chain(merged_legs);
+
+    match cli.format {
+        Format::Csv => std::fs::write("data.csv", flights::csv::serialize(legs_out))?,
+        Format::Ndjson => {
+            std::fs::write("data.ndjson", flights::csv::serialize_ndjson(legs_out))?
         }
     }
-    let data_csv = wtr.into_inner().unwrap();
-    std::fs::write("data.csv", data_csv)?;
 
     let number_of_private_jets = Fact {
         claim: legs.iter().filter(|x| x.1.len() > 0).count().to_formatted_string(&Locale::en),
@@ -391,10 +500,44 @@ async fn main() -> Result<(), Box<dyn Error>> {
     let commercial_emissions_short = commercial_emissions(&legs, |leg| leg.distance() < 300.0);
 
     let short_ratio = leg_per_person(emissions_short_legs) / commercial_emissions_short;
-    let ratio_train_300km = Fact {
-        claim: (short_ratio + 7.0) as usize,
-        source: format!("{}x in comparison to a commercial flight[^1][^6] plus 7x of a commercial flight in comparison to a train, as per https://ourworldindata.org/travel-carbon-footprint (UK data, vary by country) - retrieved on 2024-01-20", short_ratio as usize),
-        date: now.to_string()
+
+    // compare to a real rail alternative when a GTFS feed was provided and covers the
+    // <300km legs flown; otherwise fall back to the flat multiplier
+    let short_legs_iter = || {
+        legs.iter()
+            .flat_map(|(_, legs)| legs.iter().filter(|leg| leg.distance() < 300.0))
+    };
+    let has_rail_coverage = |leg: &&Leg| {
+        gtfs.as_ref()
+            .is_some_and(|gtfs| gtfs.rail_emissions_kg(leg.from().pos(), leg.to().pos()).is_some())
+    };
+    let rail_emissions_kg: Option<f64> = gtfs.as_ref().map(|gtfs| {
+        short_legs_iter()
+            .filter_map(|leg| gtfs.rail_emissions_kg(leg.from().pos(), leg.to().pos()))
+            .sum()
+    });
+
+    let ratio_train_300km = match rail_emissions_kg.filter(|kg| *kg > 0.0) {
+        Some(rail_emissions_kg) => {
+            // restrict the numerator to the same rail-covered legs the denominator summed, so a
+            // GTFS feed that only covers part of the short-leg set doesn't inflate the ratio with
+            // jet emissions from routes that contributed nothing to `rail_emissions_kg`.
+            let rail_covered_emissions_short = private_emissions(&legs, &models, |leg| {
+                leg.distance() < 300.0 && has_rail_coverage(leg)
+            });
+            let train_ratio =
+                leg_per_person(rail_covered_emissions_short) / (rail_emissions_kg / 1000.0);
+            Fact {
+                claim: (short_ratio + train_ratio) as usize,
+                source: format!("{}x in comparison to a commercial flight[^1][^6] plus {}x of a commercial flight in comparison to the GTFS-derived rail alternative (stations within {MAX_STOP_DISTANCE_KM:.0}km of each leg's endpoints, {RAIL_KG_CO2E_PER_PASSENGER_KM} kg CO2e/passenger-km) for the GTFS feed at {gtfs_file}", short_ratio as usize, train_ratio as usize, gtfs_file = cli.gtfs_file.as_ref().expect("gtfs_file to be set when gtfs is Some").display()),
+                date: now.to_string(),
+            }
+        }
+        None => Fact {
+            claim: (short_ratio + 7.0) as usize,
+            source: format!("{}x in comparison to a commercial flight[^1][^6] plus 7x of a commercial flight in comparison to a train, as per https://ourworldindata.org/travel-carbon-footprint (UK data, vary by country) - retrieved on 2024-01-20", short_ratio as usize),
+            date: now.to_string()
+        },
     };
 
     // compute emissions for the >300km legs, so we can compare with emissions from commercial flights
@@ -418,9 +561,13 @@ async fn main() -> Result<(), Box<dyn Error>> {
 
     let context = Context {
         country: cli.country.to_context(),
-        location: cli
-            .location
-            .map(|l| format!(" at {}", l.name()))
+        location: (!regions.is_empty())
+            .then(|| {
+                format!(
+                    " at {}",
+                    regions.iter().map(|r| r.name.as_str()).collect::<Vec<_>>().join(", ")
+                )
+            })
             .unwrap_or_default(),
         from_date,
         to_date,
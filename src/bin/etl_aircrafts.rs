@@ -1,20 +1,26 @@
 use std::collections::HashMap;
 use std::error::Error;
+use std::sync::atomic::{AtomicUsize, Ordering};
 
 use clap::Parser;
 use futures::StreamExt;
 use futures::TryStreamExt;
 use simple_logger::SimpleLogger;
+use time::macros::date;
 use time::Date;
 
 use flights::aircraft;
 use flights::fs;
 use flights::fs::BlobStorageProvider;
+use flights::fs_pg::PgStore;
+use flights::sd_notify;
 
-#[derive(clap::ValueEnum, Debug, Clone)]
+#[derive(clap::ValueEnum, Debug, Clone, Copy)]
 enum Backend {
     Disk,
     Remote,
+    /// A Postgres database reachable at `--database-url`, see [`PgStore`].
+    Postgres,
 }
 
 async fn write_csv(
@@ -30,17 +36,36 @@ async fn write_csv(
 const ABOUT: &'static str = r#"Creates a new snapshot of the database of all worldwide aircrafts according to `M-aircrafts-in-time`.
 This ETL is append only - every time it runs, it creates a new snapshot.
 If `access_key` and `secret_access_key` are not provided, data is written to the local disk.
+Pass `--backend postgres --database-url ...` to write into a Postgres database instead.
 "#;
 
 #[derive(Parser, Debug)]
 #[command(author, version, about = ABOUT)]
 struct Cli {
+    /// Where to write the snapshot
+    #[arg(long, value_enum, default_value_t=Backend::Remote)]
+    backend: Backend,
     /// The token to the remote storage
     #[arg(long)]
     access_key: Option<String>,
     /// The token to the remote storage
     #[arg(long)]
     secret_access_key: Option<String>,
+    /// The `postgres://` connection string, required when `--backend postgres`
+    #[arg(long)]
+    database_url: Option<String>,
+    /// Only re-parse the ICAO prefixes whose upstream content changed since the last run's sync
+    /// token (every prefix is still fetched to detect changes), see
+    /// [`aircraft::etl_aircrafts_incremental`].
+    #[arg(long)]
+    incremental: bool,
+    /// Start of the time window of `private_aircraft` snapshots to (re)write, in format
+    /// `yyyy-mm-dd`; defaults to 2019-01-01
+    #[arg(long, value_parser = parse_date)]
+    from: Option<time::Date>,
+    /// End of the time window (inclusive), in format `yyyy-mm-dd`; defaults to 2030-01-01
+    #[arg(long, value_parser = parse_date)]
+    to: Option<time::Date>,
 }
 
 fn pk_to_blob_name(month: time::Date) -> String {
@@ -48,6 +73,20 @@ fn pk_to_blob_name(month: time::Date) -> String {
     format!("private_aircraft/v1/month={month}/data.csv")
 }
 
+fn parse_date(arg: &str) -> Result<time::Date, time::error::Parse> {
+    time::Date::parse(
+        arg,
+        time::macros::format_description!("[year]-[month]-[day]"),
+    )
+}
+
+/// The first day of `date`'s month, so a caller-provided day-of-month doesn't cause an
+/// off-by-one when compared against the month-granularity keys [`flights::private_jets_in_month`]
+/// returns.
+fn month_floor(date: time::Date) -> time::Date {
+    time::Date::from_calendar_date(date.year(), date.month(), 1).expect("day 1 never errors")
+}
+
 #[tokio::main(flavor = "multi_thread")]
 async fn main() -> Result<(), Box<dyn Error>> {
     SimpleLogger::new()
@@ -58,38 +97,84 @@ async fn main() -> Result<(), Box<dyn Error>> {
     let cli = Cli::parse();
 
     // initialize client
-    let client = match (cli.access_key, cli.secret_access_key) {
-        (Some(access_key), Some(secret_access_key)) => {
-            Some(flights::fs_s3::client(access_key, secret_access_key).await)
-        }
-        (None, None) => None,
-        _ => {
-            return Err("both access_key and secret_access_key must be provided or neither".into())
+    let client: Box<dyn fs::BlobStorageProvider> = match cli.backend {
+        Backend::Disk => Box::new(fs::LocalDisk),
+        Backend::Remote => match (cli.access_key, cli.secret_access_key) {
+            (Some(access_key), Some(secret_access_key)) => {
+                Box::new(flights::fs_s3::client(access_key, secret_access_key).await)
+            }
+            (None, None) => Box::new(fs::LocalDisk),
+            _ => {
+                return Err(
+                    "both access_key and secret_access_key must be provided or neither".into(),
+                )
+            }
+        },
+        Backend::Postgres => {
+            let database_url = cli
+                .database_url
+                .ok_or("--database-url is required for --backend postgres")?;
+            Box::new(PgStore::connect(&database_url).await?)
         }
     };
-    let client = client
-        .as_ref()
-        .map(|x| x as &dyn fs::BlobStorageProvider)
-        .unwrap_or(&fs::LocalDisk);
-
-    log::info!("Fetching and writing all aircrafts");
-    aircraft::etl_aircrafts(client).await?;
-    log::info!("All aircrafts written");
+    let client = client.as_ref();
+    sd_notify::ready()?;
+
+    // Ping the watchdog on whatever interval `WatchdogSec=` asked for, so a hung remote fetch
+    // gets the unit restarted instead of silently stalling forever.
+    if let Some(interval) = sd_notify::watchdog_interval() {
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                let _ = sd_notify::watchdog();
+            }
+        });
+    }
+
+    if cli.incremental {
+        log::info!("Fetching and writing changed aircrafts only");
+        sd_notify::status("incrementally refreshing the worldwide aircraft database")?;
+        let delta = aircraft::etl_aircrafts_incremental(client).await?;
+        log::info!(
+            "Aircrafts refreshed to token {}: {} added, {} changed, {} removed",
+            delta.manifest.token,
+            delta.added.len(),
+            delta.changed.len(),
+            delta.removed.len()
+        );
+    } else {
+        log::info!("Fetching and writing all aircrafts");
+        sd_notify::status("fetching the worldwide aircraft database")?;
+        aircraft::etl_aircrafts(client).await?;
+        log::info!("All aircrafts written");
+    }
 
     // write private jets to dedicated place.
     log::info!("Writing all models");
+    sd_notify::status("writing private jet models")?;
     let data = std::fs::read_to_string("src/models.csv")?;
     client
         .put("model/db/data.csv", data.as_bytes().to_vec())
         .await?;
     log::info!("All models written");
 
+    let from = cli.from.unwrap_or(date!(2019 - 01 - 01));
+    let to = cli.to.unwrap_or(date!(2030 - 01 - 01));
+    let (from_month, to_month) = (month_floor(from), month_floor(to));
+
     log::info!("Fetching all private aircrafts in time");
-    let tasks = flights::private_jets_in_month(2019..2030, None, client).await?;
+    sd_notify::status(&format!(
+        "fetching all private aircrafts in time ({from}-{to})"
+    ))?;
+    let tasks =
+        flights::private_jets_in_month(from.year()..=to.year(), &flights::Filter::default(), client)
+            .await?;
 
     let by_month = tasks
         .into_iter()
         .map(|((_, date), (aircraft, _))| (date, aircraft))
+        .filter(|(date, _)| *date >= from_month && *date <= to_month)
         .fold(
             HashMap::<Date, Vec<_>>::new(),
             |mut acc, (date, aircraft)| {
@@ -102,13 +187,23 @@ async fn main() -> Result<(), Box<dyn Error>> {
             },
         );
 
-    let tasks = by_month.into_iter().map(|(date, aircrafts)| async move {
-        write_csv(aircrafts.into_iter(), &pk_to_blob_name(date), client).await
+    let total_months = by_month.len();
+    let written_months = AtomicUsize::new(0);
+    let tasks = by_month.into_iter().map(|(date, aircrafts)| {
+        let written_months = &written_months;
+        async move {
+            write_csv(aircrafts.into_iter(), &pk_to_blob_name(date), client).await?;
+            let done = written_months.fetch_add(1, Ordering::Relaxed) + 1;
+            let _ = sd_notify::status(&format!(
+                "writing private aircrafts in time: month {date} done ({done}/{total_months})"
+            ));
+            Ok(())
+        }
     });
 
     futures::stream::iter(tasks)
         .buffered(400)
-        .try_collect::<Vec<_>>()
+        .try_collect::<Vec<()>>()
         .await?;
 
     log::info!("All private aircrafts in time written");
@@ -1,11 +1,18 @@
 use std::{
     collections::{HashMap, HashSet},
     error::Error,
+    sync::atomic::{AtomicU64, Ordering},
     sync::Arc,
 };
 
+use arrow::array::{Array, ArrayRef, Float64Array, StringArray, TimestampMicrosecondArray};
+use arrow::datatypes::{DataType, Field, Schema, TimeUnit};
+use arrow::record_batch::RecordBatch;
 use clap::Parser;
 use futures::{StreamExt, TryStreamExt};
+use parquet::arrow::{ArrowWriter, arrow_reader::ParquetRecordBatchReaderBuilder};
+use parquet::basic::Compression;
+use parquet::file::properties::WriterProperties;
 use serde::Serialize;
 use simple_logger::SimpleLogger;
 
@@ -13,6 +20,81 @@ use flights::{aircraft::Aircraft, fs::BlobStorageProvider, model::AircraftModel,
 
 static DATABASE_ROOT: &'static str = "leg/v2/";
 static DATABASE: &'static str = "leg/v2/data/";
+/// Append-only log of per-`(icao, month)` content hashes, used to skip re-transforming unchanged
+/// months and to decide which yearly rollups need rebuilding. Also the change log that
+/// [`changes_since`] walks to serve incremental sync requests.
+static INDEX: &'static str = "leg/v2/index.csv";
+/// The sync-token watermark: the next token to allocate, and the oldest token still reliably
+/// answerable from [`INDEX`] after [`prune_index`] has collapsed older entries away.
+static SYNC_STATE: &'static str = "leg/v2/sync/state.json";
+/// Root of the per-`(icao_number, month)` partition stats blobs (see [`PartitionStats`]), a
+/// K2V-style index a dashboard can read directly instead of downloading and scanning [`DATABASE`].
+static PARTITION_INDEX: &'static str = "leg/v1/index/";
+
+/// Per-partition totals kept alongside the leg data, so a dashboard can learn how many legs a
+/// `(icao_number, month)` has and how much they emitted without reading and summing its
+/// `data.{csv,parquet}`. `token` borrows the same monotonic counter as [`IndexEntry::idx`], so
+/// [`poll`] can tell whether a partition moved since a caller last looked without comparing the
+/// counts themselves.
+#[derive(serde::Serialize, serde::Deserialize, Clone, Copy)]
+struct PartitionStats {
+    leg_count: usize,
+    total_emissions_kg: f64,
+    token: u64,
+}
+
+fn partition_stats_key(icao_number: &str, month: time::Date) -> String {
+    let month = flights::serde::month_to_part(month);
+    format!("{PARTITION_INDEX}month={month}/icao_number={icao_number}/stats.json")
+}
+
+/// Reads a `(icao_number, month)`'s current [`PartitionStats`], or `None` if it has never been
+/// built.
+async fn read_partition_stats(
+    icao_number: &str,
+    month: time::Date,
+    client: &dyn BlobStorageProvider,
+) -> Result<Option<PartitionStats>, Box<dyn Error>> {
+    let key = partition_stats_key(icao_number, month);
+    Ok(match client.maybe_get(&key).await? {
+        Some(data) => Some(serde_json::from_slice(&data)?),
+        None => None,
+    })
+}
+
+/// Returns the partition's current stats if they moved past `last_seen`, or `None` if there is
+/// nothing new to report. A dashboard watching a specific aircraft can poll this repeatedly
+/// instead of re-downloading and re-summing the leg data on every check.
+async fn poll(
+    icao_number: &str,
+    month: time::Date,
+    last_seen: u64,
+    client: &dyn BlobStorageProvider,
+) -> Result<Option<PartitionStats>, Box<dyn Error>> {
+    Ok(read_partition_stats(icao_number, month, client)
+        .await?
+        .filter(|stats| stats.token > last_seen))
+}
+
+/// The on-disk format for a `data.{csv,parquet}` leg file; Parquet's column compression makes
+/// the per-year `all/year=/data.*` rollups dramatically smaller and lets downstream analytics
+/// engines read a column subset (e.g. just `co2_emissions` and `distance`) without parsing
+/// every field.
+#[derive(clap::ValueEnum, Debug, Clone, Copy, Default, PartialEq, Eq)]
+enum Format {
+    #[default]
+    Csv,
+    Parquet,
+}
+
+impl Format {
+    fn extension(&self) -> &'static str {
+        match self {
+            Self::Csv => "csv",
+            Self::Parquet => "parquet",
+        }
+    }
+}
 
 #[derive(serde::Serialize, serde::Deserialize)]
 struct LegOut {
@@ -54,11 +136,178 @@ struct LegOut {
     co2_emissions: f64,
 }
 
-#[derive(serde::Serialize)]
+#[derive(serde::Serialize, serde::Deserialize, Clone)]
 struct Metadata {
     icao_months_to_process: usize,
     icao_months_processed: usize,
     url: String,
+    /// The highest index-log `idx` among this year's members at the time of this rollup; used
+    /// to tell whether the year needs rebuilding on a later run
+    max_idx: u64,
+    /// Leg count and total emissions for this year, broken down by `icao_number`, so a consumer
+    /// doesn't have to download the rollup just to know which aircraft it covers
+    ///
+    /// `#[serde(default)]` so a `status.json` written before this field existed still
+    /// deserializes, instead of failing `read_json` on the very next run.
+    #[serde(default)]
+    aircraft_totals: HashMap<Arc<str>, AircraftTotal>,
+}
+
+/// One aircraft's contribution to a year's [`Metadata`].
+#[derive(serde::Serialize, serde::Deserialize, Clone, Copy, Default)]
+struct AircraftTotal {
+    leg_count: usize,
+    total_emissions_kg: f64,
+}
+
+/// What happened to a `(icao_number, month)` partition at a given index-log entry, mirroring a
+/// WebDAV sync-collection report. Carried alongside `hash` (which still drives [`etl_task`]'s
+/// skip-unchanged logic) so a [`changes_since`] caller can tell a brand-new partition from one
+/// that merely changed.
+#[derive(serde::Serialize, serde::Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+enum ChangeOp {
+    Added,
+    Changed,
+    Removed,
+}
+
+/// One row of the append-only index log at [`INDEX`]. The log only grows; the current state of
+/// a `(icao_number, month)` is its entry with the highest `idx`. A `None` hash (and `op:
+/// Removed`) is a tombstone, recording that this month is no longer among the required tasks
+/// (its source data is no longer tracked).
+#[derive(serde::Serialize, serde::Deserialize, Clone)]
+struct IndexEntry {
+    idx: u64,
+    icao_number: Arc<str>,
+    month: String,
+    hash: Option<u64>,
+    op: ChangeOp,
+}
+
+/// The persisted sync-token watermark at [`SYNC_STATE`].
+#[derive(serde::Serialize, serde::Deserialize, Clone, Default)]
+struct SyncState {
+    /// The token to hand out on the next run's high-water mark
+    next_token: u64,
+    /// The oldest token [`changes_since`] can still answer for; below this, [`INDEX`]'s tail has
+    /// been pruned and a caller must do a full resync instead
+    oldest_token: u64,
+}
+
+/// What [`changes_since`] returns for a sync request.
+enum SyncResult {
+    /// The `(icao_number, month, op)` partitions changed at or after the requested token,
+    /// deduplicated to each key's most recent operation, plus the new high-water token to
+    /// present on the next call.
+    Changes {
+        changes: Vec<(Arc<str>, time::Date, ChangeOp)>,
+        token: u64,
+    },
+    /// The requested token is older than the retained log tail: the caller fell too far behind
+    /// to be served incrementally and must re-download the whole dataset.
+    FullResyncRequired { token: u64 },
+}
+
+/// Returns the partitions changed since `token` (inclusive), or tells the caller to do a full
+/// resync if `token` predates what [`prune_index`] still retains.
+fn changes_since(token: u64, index: &[IndexEntry], state: &SyncState) -> SyncResult {
+    let new_token = index.iter().map(|e| e.idx).max().map_or(token, |m| m + 1);
+    if token < state.oldest_token {
+        return SyncResult::FullResyncRequired { token: new_token };
+    }
+
+    let mut latest_since = HashMap::<(Arc<str>, time::Date), IndexEntry>::new();
+    for entry in index.iter().filter(|e| e.idx >= token) {
+        let key = (entry.icao_number.clone(), flights::serde::parse_month(&entry.month));
+        latest_since
+            .entry(key)
+            .and_modify(|latest: &mut IndexEntry| {
+                if entry.idx > latest.idx {
+                    *latest = entry.clone();
+                }
+            })
+            .or_insert_with(|| entry.clone());
+    }
+
+    let changes = latest_since
+        .into_values()
+        .map(|e| (e.icao_number, flights::serde::parse_month(&e.month), e.op))
+        .collect();
+    SyncResult::Changes { changes, token: new_token }
+}
+
+/// How many of the most recent index-log entries are kept (each `(icao_number, month)` keeps
+/// only its latest entry among them). Once the log grows past this, older entries are collapsed
+/// down to just the latest one per `(icao_number, month)` --
+/// enough to keep [`etl_task`]'s skip-unchanged check correct, but not enough to reconstruct the
+/// full change history before the cutoff. A [`changes_since`] caller whose token falls before
+/// the cutoff is told to do a full resync instead.
+const RETAIN_LOG_ENTRIES: usize = 20_000;
+
+/// Collapses `index` down to at most [`RETAIN_LOG_ENTRIES`] most-recent entries plus each key's
+/// latest entry (even if older), and returns the oldest token for which [`changes_since`] can
+/// still reconstruct a complete change set.
+fn prune_index(index: Vec<IndexEntry>) -> (Vec<IndexEntry>, u64) {
+    if index.len() <= RETAIN_LOG_ENTRIES {
+        let oldest_token = index.iter().map(|e| e.idx).min().unwrap_or(0);
+        return (index, oldest_token);
+    }
+
+    let mut idxs: Vec<u64> = index.iter().map(|e| e.idx).collect();
+    idxs.sort_unstable();
+    let cutoff = idxs[idxs.len() - RETAIN_LOG_ENTRIES];
+
+    let latest = latest_entries(&index);
+    let mut kept = HashMap::<(Arc<str>, time::Date), IndexEntry>::new();
+    for entry in index {
+        if entry.idx >= cutoff {
+            let key = (entry.icao_number.clone(), flights::serde::parse_month(&entry.month));
+            kept.insert(key, entry);
+        }
+    }
+    for (key, entry) in latest {
+        kept.entry(key).or_insert(entry);
+    }
+
+    let mut result = kept.into_values().collect::<Vec<_>>();
+    result.sort_by_key(|e| e.idx);
+    (result, cutoff)
+}
+
+/// Hashes `positions`, so [`etl_task`] can tell whether an `(icao, month)`'s source data changed
+/// since the last run without re-running [`transform`] and [`write`].
+fn positions_hash(positions: &[Position]) -> u64 {
+    use std::hash::{Hash, Hasher};
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    serde_json::to_vec(positions)
+        .expect("positions to serialize")
+        .hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Reads the index log, or an empty one if it has never been written.
+async fn load_index(client: &dyn BlobStorageProvider) -> Result<Vec<IndexEntry>, Box<dyn Error>> {
+    Ok(match client.maybe_get(INDEX).await? {
+        Some(data) => flights::csv::deserialize::<IndexEntry>(&data).collect(),
+        None => Vec::new(),
+    })
+}
+
+/// Reduces the append-only index log to its latest (highest-`idx`) entry per `(icao_number,
+/// month)`.
+fn latest_entries(entries: &[IndexEntry]) -> HashMap<(Arc<str>, time::Date), IndexEntry> {
+    entries.iter().fold(HashMap::new(), |mut acc, entry| {
+        let key = (entry.icao_number.clone(), flights::serde::parse_month(&entry.month));
+        acc.entry(key)
+            .and_modify(|latest: &mut IndexEntry| {
+                if entry.idx > latest.idx {
+                    *latest = entry.clone();
+                }
+            })
+            .or_insert_with(|| entry.clone());
+        acc
+    })
 }
 
 async fn write_json(
@@ -72,6 +321,17 @@ async fn write_json(
     Ok(client.put(key, bytes).await?)
 }
 
+/// Reads `key` as JSON, or `D::default()` (e.g. an empty map) if it has never been written.
+async fn read_json<D: for<'de> serde::Deserialize<'de> + Default>(
+    client: &dyn BlobStorageProvider,
+    key: &str,
+) -> Result<D, Box<dyn Error>> {
+    Ok(match client.maybe_get(key).await? {
+        Some(data) => serde_json::from_slice(&data)?,
+        None => D::default(),
+    })
+}
+
 async fn write_csv(
     items: impl Iterator<Item = impl Serialize>,
     key: &str,
@@ -82,6 +342,165 @@ async fn write_csv(
     Ok(())
 }
 
+/// Microseconds since the Unix epoch, the precision [`leg_schema`]'s `start`/`end` columns are
+/// stored at.
+fn to_micros(dt: time::OffsetDateTime) -> i64 {
+    (dt.unix_timestamp_nanos() / 1_000) as i64
+}
+
+fn from_micros(micros: i64) -> Result<time::OffsetDateTime, time::error::ComponentRange> {
+    time::OffsetDateTime::from_unix_timestamp_nanos(micros as i128 * 1_000)
+}
+
+/// The Arrow schema used to read and write [`LegOut`] as Parquet, field-for-field in
+/// declaration order. `start`/`end` are native `Timestamp` columns (rather than RFC3339 text) so
+/// a reader can push a time-range filter down to the row-group level instead of parsing strings.
+fn leg_schema() -> Schema {
+    let timestamp = DataType::Timestamp(TimeUnit::Microsecond, Some("UTC".into()));
+    Schema::new(vec![
+        Field::new("icao_number", DataType::Utf8, false),
+        Field::new("tail_number", DataType::Utf8, false),
+        Field::new("aircraft_model", DataType::Utf8, false),
+        Field::new("start", timestamp.clone(), false),
+        Field::new("start_lat", DataType::Float64, false),
+        Field::new("start_lon", DataType::Float64, false),
+        Field::new("start_altitude", DataType::Float64, false),
+        Field::new("end", timestamp, false),
+        Field::new("end_lat", DataType::Float64, false),
+        Field::new("end_lon", DataType::Float64, false),
+        Field::new("end_altitude", DataType::Float64, false),
+        Field::new("duration", DataType::Float64, false),
+        Field::new("distance", DataType::Float64, false),
+        Field::new("great_circle_distance", DataType::Float64, false),
+        Field::new("hours_above_30000", DataType::Float64, false),
+        Field::new("hours_above_40000", DataType::Float64, false),
+        Field::new("co2_emissions", DataType::Float64, false),
+    ])
+}
+
+fn legs_to_record_batch(legs: &[LegOut]) -> Result<RecordBatch, Box<dyn Error>> {
+    let columns: Vec<ArrayRef> = vec![
+        Arc::new(StringArray::from_iter_values(
+            legs.iter().map(|l| l.icao_number.as_ref()),
+        )),
+        Arc::new(StringArray::from_iter_values(
+            legs.iter().map(|l| l.tail_number.as_ref()),
+        )),
+        Arc::new(StringArray::from_iter_values(
+            legs.iter().map(|l| l.aircraft_model.as_ref()),
+        )),
+        Arc::new(
+            TimestampMicrosecondArray::from_iter_values(legs.iter().map(|l| to_micros(l.start)))
+                .with_timezone("UTC"),
+        ),
+        Arc::new(Float64Array::from_iter_values(legs.iter().map(|l| l.start_lat))),
+        Arc::new(Float64Array::from_iter_values(legs.iter().map(|l| l.start_lon))),
+        Arc::new(Float64Array::from_iter_values(
+            legs.iter().map(|l| l.start_altitude),
+        )),
+        Arc::new(
+            TimestampMicrosecondArray::from_iter_values(legs.iter().map(|l| to_micros(l.end)))
+                .with_timezone("UTC"),
+        ),
+        Arc::new(Float64Array::from_iter_values(legs.iter().map(|l| l.end_lat))),
+        Arc::new(Float64Array::from_iter_values(legs.iter().map(|l| l.end_lon))),
+        Arc::new(Float64Array::from_iter_values(legs.iter().map(|l| l.end_altitude))),
+        Arc::new(Float64Array::from_iter_values(legs.iter().map(|l| l.duration))),
+        Arc::new(Float64Array::from_iter_values(legs.iter().map(|l| l.distance))),
+        Arc::new(Float64Array::from_iter_values(
+            legs.iter().map(|l| l.great_circle_distance),
+        )),
+        Arc::new(Float64Array::from_iter_values(
+            legs.iter().map(|l| l.hours_above_30000),
+        )),
+        Arc::new(Float64Array::from_iter_values(
+            legs.iter().map(|l| l.hours_above_40000),
+        )),
+        Arc::new(Float64Array::from_iter_values(legs.iter().map(|l| l.co2_emissions))),
+    ];
+    Ok(RecordBatch::try_new(Arc::new(leg_schema()), columns)?)
+}
+
+/// Writes `legs` as Parquet with Snappy column compression, which makes the yearly rollups
+/// dramatically smaller than CSV and lets downstream readers project only the columns they need.
+async fn write_parquet(
+    legs: impl Iterator<Item = LegOut>,
+    key: &str,
+    client: &dyn BlobStorageProvider,
+) -> Result<(), Box<dyn Error>> {
+    let legs = legs.collect::<Vec<_>>();
+    let batch = legs_to_record_batch(&legs)?;
+
+    let properties = WriterProperties::builder()
+        .set_compression(Compression::SNAPPY)
+        .build();
+    let mut buffer = Vec::new();
+    let mut writer = ArrowWriter::try_new(&mut buffer, batch.schema(), Some(properties))?;
+    writer.write(&batch)?;
+    writer.close()?;
+
+    client.put(key, buffer).await?;
+    Ok(())
+}
+
+/// Reads a Parquet file previously written by [`write_parquet`] back into `LegOut` rows.
+fn read_parquet(data: Vec<u8>) -> Result<Vec<LegOut>, Box<dyn Error>> {
+    let reader = ParquetRecordBatchReaderBuilder::try_new(bytes::Bytes::from(data))?.build()?;
+
+    let mut legs = Vec::new();
+    for batch in reader {
+        let batch = batch?;
+        let icao_number = batch.column(0).as_any().downcast_ref::<StringArray>().unwrap();
+        let tail_number = batch.column(1).as_any().downcast_ref::<StringArray>().unwrap();
+        let aircraft_model = batch.column(2).as_any().downcast_ref::<StringArray>().unwrap();
+        let start = batch
+            .column(3)
+            .as_any()
+            .downcast_ref::<TimestampMicrosecondArray>()
+            .unwrap();
+        let start_lat = batch.column(4).as_any().downcast_ref::<Float64Array>().unwrap();
+        let start_lon = batch.column(5).as_any().downcast_ref::<Float64Array>().unwrap();
+        let start_altitude = batch.column(6).as_any().downcast_ref::<Float64Array>().unwrap();
+        let end = batch
+            .column(7)
+            .as_any()
+            .downcast_ref::<TimestampMicrosecondArray>()
+            .unwrap();
+        let end_lat = batch.column(8).as_any().downcast_ref::<Float64Array>().unwrap();
+        let end_lon = batch.column(9).as_any().downcast_ref::<Float64Array>().unwrap();
+        let end_altitude = batch.column(10).as_any().downcast_ref::<Float64Array>().unwrap();
+        let duration = batch.column(11).as_any().downcast_ref::<Float64Array>().unwrap();
+        let distance = batch.column(12).as_any().downcast_ref::<Float64Array>().unwrap();
+        let great_circle_distance = batch.column(13).as_any().downcast_ref::<Float64Array>().unwrap();
+        let hours_above_30000 = batch.column(14).as_any().downcast_ref::<Float64Array>().unwrap();
+        let hours_above_40000 = batch.column(15).as_any().downcast_ref::<Float64Array>().unwrap();
+        let co2_emissions = batch.column(16).as_any().downcast_ref::<Float64Array>().unwrap();
+
+        for row in 0..batch.num_rows() {
+            legs.push(LegOut {
+                icao_number: icao_number.value(row).into(),
+                tail_number: tail_number.value(row).into(),
+                aircraft_model: aircraft_model.value(row).into(),
+                start: from_micros(start.value(row))?,
+                start_lat: start_lat.value(row),
+                start_lon: start_lon.value(row),
+                start_altitude: start_altitude.value(row),
+                end: from_micros(end.value(row))?,
+                end_lat: end_lat.value(row),
+                end_lon: end_lon.value(row),
+                end_altitude: end_altitude.value(row),
+                duration: duration.value(row),
+                distance: distance.value(row),
+                great_circle_distance: great_circle_distance.value(row),
+                hours_above_30000: hours_above_30000.value(row),
+                hours_above_40000: hours_above_40000.value(row),
+                co2_emissions: co2_emissions.value(row),
+            });
+        }
+    }
+    Ok(legs)
+}
+
 fn transform<'a>(
     icao_number: &'a Arc<str>,
     aircraft: &'a Aircraft,
@@ -128,12 +547,16 @@ fn transform<'a>(
 async fn write(
     icao: &Arc<str>,
     month: time::Date,
-    legs: impl Iterator<Item = impl Serialize>,
+    legs: impl Iterator<Item = LegOut>,
+    format: Format,
     client: &dyn BlobStorageProvider,
 ) -> Result<(), Box<dyn Error>> {
-    let key = pk_to_blob_name(icao, month);
+    let key = pk_to_blob_name(icao, month, format);
 
-    write_csv(legs, &key, client).await?;
+    match format {
+        Format::Csv => write_csv(legs, &key, client).await?,
+        Format::Parquet => write_parquet(legs, &key, client).await?,
+    }
     log::info!("Written {} {}", icao, month);
     Ok(())
 }
@@ -141,19 +564,43 @@ async fn write(
 async fn read_u8(
     icao: &Arc<str>,
     month: time::Date,
+    format: Format,
     client: &dyn BlobStorageProvider,
 ) -> Result<Option<Vec<u8>>, std::io::Error> {
     log::info!("Read icao={icao} month={month}");
-    client.maybe_get(&pk_to_blob_name(icao, month)).await
+    client.maybe_get(&pk_to_blob_name(icao, month, format)).await
 }
 
-fn pk_to_blob_name(icao: &str, month: time::Date) -> String {
+fn pk_to_blob_name(icao: &str, month: time::Date, format: Format) -> String {
     let month = flights::serde::month_to_part(month);
-    format!("{DATABASE}month={month}/icao_number={icao}/data.csv")
+    format!(
+        "{DATABASE}month={month}/icao_number={icao}/data.{}",
+        format.extension()
+    )
+}
+
+/// Parses the rows of a blob previously written by [`write`], regardless of `format`.
+fn read_legs(data: Vec<u8>, format: Format) -> Result<Vec<LegOut>, Box<dyn Error>> {
+    match format {
+        Format::Csv => flights::csv::deserialize::<LegOut>(&data).collect(),
+        Format::Parquet => read_parquet(data),
+    }
 }
 
 const ABOUT: &'static str = "Builds the database of all legs";
 
+/// Where completed legs are written. `Blob` is the existing `data.{csv,parquet}`-per-partition
+/// path with its `all/year=` rollups; `Postgres` upserts typed rows into the `legs` table
+/// instead, so ad-hoc filtered questions can be answered with SQL via
+/// [`flights::fs_pg::PgStore::query_legs`] rather than downloading and flattening every
+/// partition.
+#[derive(clap::ValueEnum, Debug, Clone, Copy, Default, PartialEq, Eq)]
+enum Backend {
+    #[default]
+    Blob,
+    Postgres,
+}
+
 #[derive(Parser, Debug)]
 #[command(author, version, about = ABOUT)]
 struct Cli {
@@ -166,26 +613,128 @@ struct Cli {
     /// Optional country to fetch from (in ISO 3166); defaults to whole world
     #[arg(long)]
     country: Option<String>,
+    /// The format used to write `data.{csv,parquet}`, both per-icao-month and in the yearly
+    /// `all/year=` rollups. Ignored when `--backend postgres`
+    #[arg(long, value_enum, default_value_t=Format::Csv)]
+    format: Format,
+    /// Where to write completed legs
+    #[arg(long, value_enum, default_value_t=Backend::Blob)]
+    backend: Backend,
+    /// Postgres connection string (`postgres://...`); required when `--backend postgres`
+    #[arg(long)]
+    postgres_url: Option<String>,
+    /// If set, don't build the database: instead report the `(icao_number, month)` partitions
+    /// changed since this sync token and exit. Pass the token a prior `--since` run (or this
+    /// run's build) returned, to fetch only what's new
+    #[arg(long)]
+    since: Option<u64>,
+    /// If set (together with `--month`), don't build the database: instead poll a single
+    /// partition's leg count/total emissions/token and exit, printing it only if it moved past
+    /// `--last-seen`
+    #[arg(long, requires = "month")]
+    icao_number: Option<String>,
+    /// A month in format `yyyy-mm-dd` (the day is ignored); used with `--icao-number`
+    #[arg(long, value_parser = parse_date)]
+    month: Option<time::Date>,
+    /// Only report `--icao-number`/`--month`'s stats if its token moved past this value
+    #[arg(long, default_value_t = 0)]
+    last_seen: u64,
 }
 
+fn parse_date(arg: &str) -> Result<time::Date, time::error::Parse> {
+    time::Date::parse(
+        arg,
+        time::macros::format_description!("[year]-[month]-[day]"),
+    )
+}
+
+/// Extracts and hashes `(icao_number, month)`'s positions, and only re-transforms and re-writes
+/// its leg file when the hash differs from `previous_hash` (the index log's last-known hash for
+/// this key). Returns the freshly computed hash, so the caller can tell whether it changed and
+/// append an [`IndexEntry`] if so, plus this partition's leg count and total emissions when it
+/// was (re)computed this run (`None` when skipped, since the previous run's [`PartitionStats`]
+/// are still accurate).
 async fn etl_task(
     aircraft: &Aircraft,
     model: &AircraftModel,
     month: time::Date,
+    format: Format,
+    previous_hash: Option<u64>,
     client: &dyn BlobStorageProvider,
-) -> Result<(), Box<dyn Error>> {
+    pg: Option<&flights::fs_pg::PgStore>,
+) -> Result<(u64, Option<(usize, f64)>), Box<dyn Error>> {
     let icao_number = &aircraft.icao_number;
     // extract
     let positions =
         flights::icao_to_trace::get_month_positions(&icao_number, month, client).await?;
+    let hash = positions_hash(&positions);
+
+    if previous_hash == Some(hash) {
+        log::info!("Unchanged {icao_number} {month}, skipping");
+        return Ok((hash, None));
+    }
+
     // transform
-    let legs = transform(&icao_number, aircraft, model, positions);
+    let legs = transform(&icao_number, aircraft, model, positions).collect::<Vec<_>>();
+    let leg_count = legs.len();
+    let total_emissions_kg = legs.iter().map(|leg| leg.co2_emissions).sum::<f64>();
+
     // load
-    write(&icao_number, month, legs, client).await
+    match pg {
+        Some(pg) => {
+            for leg in &legs {
+                pg.upsert_leg(&leg_out_to_row(month, leg)).await?;
+            }
+            log::info!("Upserted {} {}", icao_number, month);
+        }
+        None => write(&icao_number, month, legs.into_iter(), format, client).await?,
+    }
+    Ok((hash, Some((leg_count, total_emissions_kg))))
+}
+
+/// Converts a [`LegOut`] (already computed for `month`) into the row shape
+/// [`flights::fs_pg::PgStore::upsert_leg`] expects.
+fn leg_out_to_row(month: time::Date, leg: &LegOut) -> flights::fs_pg::LegRow {
+    flights::fs_pg::LegRow {
+        icao_number: leg.icao_number.clone(),
+        tail_number: leg.tail_number.clone(),
+        aircraft_model: leg.aircraft_model.clone(),
+        month,
+        start: leg.start,
+        start_lat: leg.start_lat,
+        start_lon: leg.start_lon,
+        start_altitude: leg.start_altitude,
+        end: leg.end,
+        end_lat: leg.end_lat,
+        end_lon: leg.end_lon,
+        end_altitude: leg.end_altitude,
+        duration: leg.duration,
+        distance: leg.distance,
+        great_circle_distance: leg.great_circle_distance,
+        hours_above_30000: leg.hours_above_30000,
+        hours_above_40000: leg.hours_above_40000,
+        co2_emissions: leg.co2_emissions,
+    }
+}
+
+/// Assigns the next index-log `idx`, starting from one past the highest `idx` already in `index`.
+struct IdxAllocator(AtomicU64);
+
+impl IdxAllocator {
+    fn starting_after(index: &[IndexEntry]) -> Self {
+        let next = index.iter().map(|e| e.idx).max().map_or(0, |m| m + 1);
+        Self(AtomicU64::new(next))
+    }
+
+    fn next(&self) -> u64 {
+        self.0.fetch_add(1, Ordering::Relaxed)
+    }
 }
 
 async fn aggregate(
     required: impl Iterator<Item = (Arc<str>, time::Date)>,
+    year_max_idx: &HashMap<i32, u64>,
+    format: Format,
     client: &dyn BlobStorageProvider,
 ) -> Result<(), Box<dyn Error>> {
     // group by year
@@ -198,12 +747,20 @@ async fn aggregate(
         acc
     });
 
-    // run tasks by year
-    let mut metadata = HashMap::<i32, Metadata>::new();
+    let key = format!("{DATABASE_ROOT}status.json");
+    let mut metadata = read_json::<HashMap<i32, Metadata>>(client, &key).await?;
+
+    // run tasks by year, skipping years whose members haven't advanced since the last run
     for (year, completed) in required_by_year {
-        let tasks = completed
-            .iter()
-            .map(|(icao_number, date)| async move { read_u8(icao_number, *date, client).await });
+        let max_idx = year_max_idx.get(&year).copied().unwrap_or(0);
+        if metadata.get(&year).is_some_and(|m| m.max_idx == max_idx) {
+            log::info!("year={year} unchanged, skipping rebuild");
+            continue;
+        }
+
+        let tasks = completed.iter().map(|(icao_number, date)| async move {
+            read_u8(icao_number, *date, format, client).await
+        });
 
         log::info!("Gettings all legs for year={year}");
         let legs = futures::stream::iter(tasks)
@@ -212,16 +769,23 @@ async fn aggregate(
             .await?
             .into_iter()
             .flatten() // drop those that do not exist
-            .map(|content| {
-                flights::csv::deserialize::<LegOut>(&content)
-                    .collect::<Result<Vec<_>, _>>()
-                    .unwrap()
-            })
-            .flatten();
+            .map(|content| read_legs(content, format).unwrap())
+            .flatten()
+            .collect::<Vec<_>>();
+
+        let mut aircraft_totals = HashMap::<Arc<str>, AircraftTotal>::new();
+        for leg in &legs {
+            let totals = aircraft_totals.entry(leg.icao_number.clone()).or_default();
+            totals.leg_count += 1;
+            totals.total_emissions_kg += leg.co2_emissions;
+        }
 
         log::info!("Writing all legs for year={year}");
-        let key = format!("{DATABASE_ROOT}all/year={year}/data.csv");
-        write_csv(legs, &key, client).await?;
+        let key = format!("{DATABASE_ROOT}all/year={year}/data.{}", format.extension());
+        match format {
+            Format::Csv => write_csv(legs.into_iter(), &key, client).await?,
+            Format::Parquet => write_parquet(legs.into_iter(), &key, client).await?,
+        }
         log::info!("Written {key}");
         metadata.insert(
             year,
@@ -229,11 +793,12 @@ async fn aggregate(
                 icao_months_to_process: completed.len(),
                 icao_months_processed: completed.len(),
                 url: format!("https://private-jets.fra1.digitaloceanspaces.com/{key}"),
+                max_idx,
+                aircraft_totals,
             },
         );
     }
 
-    let key = format!("{DATABASE_ROOT}status.json");
     write_json(client, metadata, &key).await?;
     log::info!("status written");
     Ok(())
@@ -251,30 +816,158 @@ async fn main() -> Result<(), Box<dyn Error>> {
     let client = flights::fs_s3::client(cli.access_key, cli.secret_access_key).await;
     let client = &client;
 
+    let pg = match cli.backend {
+        Backend::Blob => None,
+        Backend::Postgres => {
+            let url = cli
+                .postgres_url
+                .as_deref()
+                .ok_or("--postgres-url is required for --backend postgres")?;
+            Some(flights::fs_pg::PgStore::connect(url).await?)
+        }
+    };
+    let pg = pg.as_ref();
+
+    if let Some(since) = cli.since {
+        let index = load_index(client).await?;
+        let state = read_json::<SyncState>(client, SYNC_STATE).await?;
+        match changes_since(since, &index, &state) {
+            SyncResult::Changes { changes, token } => {
+                for (icao_number, month, op) in &changes {
+                    println!("{icao_number},{month},{op:?}");
+                }
+                log::info!("{} changes, new token: {token}", changes.len());
+            }
+            SyncResult::FullResyncRequired { token } => {
+                log::warn!("token {since} is older than the retained log tail: full resync required, new token: {token}");
+            }
+        }
+        return Ok(());
+    }
+
+    if let Some(icao_number) = &cli.icao_number {
+        let month = cli.month.expect("clap enforces --month with --icao-number");
+        match poll(icao_number, month, cli.last_seen, client).await? {
+            Some(stats) => println!(
+                "{icao_number},{month},{},{},{}",
+                stats.leg_count, stats.total_emissions_kg, stats.token
+            ),
+            None => log::info!(
+                "no update for {icao_number} {month} since token {}",
+                cli.last_seen
+            ),
+        }
+        return Ok(());
+    }
+
     log::info!("computing required tasks...");
-    let required =
-        flights::private_jets_in_month((2019..2026).rev(), cli.country.as_deref(), client).await?;
+    let filter = flights::Filter {
+        countries: cli.country.into_iter().collect(),
+        ..Default::default()
+    };
+    let required = flights::private_jets_in_month((2019..2026).rev(), &filter, client).await?;
     log::info!("required : {}", required.len());
 
+    log::info!("loading index...");
+    let index = load_index(client).await?;
+    let latest = latest_entries(&index);
+    let idx_allocator = IdxAllocator::starting_after(&index);
+
     log::info!("executing required...");
-    let tasks = required
-        .clone()
-        .into_iter()
-        .map(|((_, month), (aircraft, model))| async move {
-            etl_task(&aircraft, &model, month, client).await
-        });
+    let tasks = required.clone().into_iter().map(|((icao, month), (aircraft, model))| {
+        let previous_hash = latest.get(&(icao.clone(), month)).and_then(|e| e.hash);
+        async move {
+            let result =
+                etl_task(&aircraft, &model, month, cli.format, previous_hash, client, pg).await;
+            (icao, month, previous_hash, result)
+        }
+    });
 
-    let _ = futures::stream::iter(tasks)
-        .buffered(400)
-        .map(|r| {
-            if let Err(e) = r {
-                log::error!("{e}");
-            }
-        })
-        .collect::<Vec<_>>()
-        .await;
+    let results = futures::stream::iter(tasks).buffered(400).collect::<Vec<_>>().await;
     log::info!("execution completed");
 
+    // append an index entry for every (icao, month) whose hash changed, and a tombstone for
+    // every previously-tracked (icao, month) that is no longer required
+    let required_keys = required.keys().cloned().collect::<HashSet<_>>();
+    let mut new_entries = Vec::new();
+    for (icao_number, month, previous_hash, result) in results {
+        match result {
+            Ok((hash, stats)) if Some(hash) != previous_hash => {
+                let idx = idx_allocator.next();
+                new_entries.push(IndexEntry {
+                    idx,
+                    icao_number: icao_number.clone(),
+                    month: flights::serde::month_to_part(month),
+                    hash: Some(hash),
+                    op: if previous_hash.is_none() {
+                        ChangeOp::Added
+                    } else {
+                        ChangeOp::Changed
+                    },
+                });
+                if let Some((leg_count, total_emissions_kg)) = stats {
+                    write_json(
+                        client,
+                        PartitionStats {
+                            leg_count,
+                            total_emissions_kg,
+                            token: idx,
+                        },
+                        &partition_stats_key(&icao_number, month),
+                    )
+                    .await?;
+                }
+            }
+            Ok(_) => {}
+            Err(e) => log::error!("{e}"),
+        }
+    }
+    for ((icao_number, month), entry) in &latest {
+        if entry.hash.is_some() && !required_keys.contains(&(icao_number.clone(), *month)) {
+            new_entries.push(IndexEntry {
+                idx: idx_allocator.next(),
+                icao_number: icao_number.clone(),
+                month: flights::serde::month_to_part(*month),
+                hash: None,
+                op: ChangeOp::Removed,
+            });
+        }
+    }
+
+    let mut index = index;
+    let index_changed = !new_entries.is_empty();
+    if index_changed {
+        log::info!("writing {} new index entries...", new_entries.len());
+        index.extend(new_entries);
+    }
+    let year_max_idx = year_max_idx(&index);
+
+    if index_changed {
+        let next_token = index.iter().map(|e| e.idx).max().map_or(0, |m| m + 1);
+        let (index, oldest_token) = prune_index(index);
+        write_csv(index.iter(), INDEX, client).await?;
+        write_json(client, SyncState { next_token, oldest_token }, SYNC_STATE).await?;
+    }
+
+    if pg.is_some() {
+        // the Postgres backend has no `all/year=` rollup to (re)build: ad-hoc queries go
+        // straight to PgStore::query_legs instead of downloading and flattening partitions
+        log::info!("legs upserted into Postgres; skipping the blob rollup");
+        return Ok(());
+    }
+
     log::info!("aggregating...");
-    aggregate(required.into_keys(), client).await
+    aggregate(required.into_keys(), &year_max_idx, cli.format, client).await
+}
+
+/// The highest index-log `idx` per calendar year of `(icao_number, month)`, used by [`aggregate`]
+/// to tell whether a year's rollup needs rebuilding.
+fn year_max_idx(index: &[IndexEntry]) -> HashMap<i32, u64> {
+    index.iter().fold(HashMap::new(), |mut acc, entry| {
+        let year = flights::serde::parse_month(&entry.month).year();
+        acc.entry(year)
+            .and_modify(|max| *max = (*max).max(entry.idx))
+            .or_insert(entry.idx);
+        acc
+    })
 }
@@ -1,23 +1,47 @@
+use std::collections::HashSet;
 use std::error::Error;
 
 use clap::Parser;
 use futures::StreamExt;
 use simple_logger::SimpleLogger;
+use time::macros::date;
 
 const ABOUT: &'static str = r#"Builds the database of all private jet positions since 2019"#;
 
+fn parse_date(arg: &str) -> Result<time::Date, time::error::Parse> {
+    time::Date::parse(
+        arg,
+        time::macros::format_description!("[year]-[month]-[day]"),
+    )
+}
+
+/// The first day of `date`'s month, so a caller-provided day-of-month doesn't cause an
+/// off-by-one when compared against the month-granularity keys [`flights::private_jets_in_month`]
+/// returns.
+fn month_floor(date: time::Date) -> time::Date {
+    time::Date::from_calendar_date(date.year(), date.month(), 1).expect("day 1 never errors")
+}
+
 #[derive(Parser, Debug)]
 #[command(author, version, about = ABOUT)]
 struct Cli {
-    /// The token to the remote storage
+    /// The token to the remote storage; if omitted (together with `--secret-access-key`), falls
+    /// back to the standard AWS credential provider chain (environment variables, shared profile
+    /// file, then instance metadata), see [`flights::fs_s3::client_from_env`]
     #[arg(long)]
-    access_key: String,
+    access_key: Option<String>,
     /// The token to the remote storage
     #[arg(long)]
-    secret_access_key: String,
+    secret_access_key: Option<String>,
     /// Optional country to fetch from (in ISO 3166); defaults to whole world
     #[arg(long)]
     country: Option<String>,
+    /// Start of the time window to build, in format `yyyy-mm-dd`; defaults to 2019-01-01
+    #[arg(long, value_parser = parse_date)]
+    from: Option<time::Date>,
+    /// End of the time window to build (inclusive), in format `yyyy-mm-dd`; defaults to today
+    #[arg(long, value_parser = parse_date)]
+    to: Option<time::Date>,
 }
 
 #[tokio::main(flavor = "multi_thread")]
@@ -29,10 +53,27 @@ async fn main() -> Result<(), Box<dyn Error>> {
 
     let cli = Cli::parse();
 
-    let client = flights::fs_s3::client(cli.access_key, cli.secret_access_key).await;
+    let client = match (cli.access_key, cli.secret_access_key) {
+        (Some(access_key), Some(secret_access_key)) => {
+            flights::fs_s3::client(access_key, secret_access_key).await
+        }
+        _ => flights::fs_s3::client_from_env().await,
+    };
+
+    let from = cli.from.unwrap_or(date!(2019 - 01 - 01));
+    let to = cli.to.unwrap_or_else(|| time::OffsetDateTime::now_utc().date());
+    let (from_month, to_month) = (month_floor(from), month_floor(to));
 
+    let filter = flights::Filter {
+        countries: cli.country.into_iter().collect(),
+        ..Default::default()
+    };
     let required =
-        flights::private_jets_in_month((2019..2025).rev(), cli.country.as_deref(), &client).await?;
+        flights::private_jets_in_month((from.year()..=to.year()).rev(), &filter, &client).await?;
+    let required = required
+        .into_keys()
+        .filter(|(_, month)| *month >= from_month && *month <= to_month)
+        .collect::<HashSet<_>>();
 
     log::info!("required : {}", required.len());
 
@@ -5,6 +5,7 @@ use flights::LocalDisk;
 use simple_logger::SimpleLogger;
 
 use flights::aircraft;
+use flights::filter;
 use flights::load_private_jet_models;
 use flights::BlobStorageProvider;
 
@@ -17,6 +18,8 @@ enum Backend {
 const ABOUT: &'static str = r#"Exports the database of all worldwide aircrafts whose primary use is to be a private jet to "data.csv"
 and its description at `description.md` (in disk).
 If `access_key` and `secret_access_key` is provided, data is written to the public blob storage instead.
+Pass `--filter` with a filter expression (see `flights::filter`) to narrow the export further, e.g.
+`--filter "country = 'Denmark' AND type_designator IN ['F2TH','FA7X']"`.
 "#;
 
 const SPECIFICATION: &'static str = r#"This dataset was created according to
@@ -43,6 +46,16 @@ struct Cli {
     secret_access_key: Option<String>,
     #[arg(short, long, value_enum, default_value_t=Backend::Remote)]
     backend: Backend,
+
+    /// Force a rebuild of the local private-jet-models binary cache instead of reusing it
+    #[arg(long)]
+    refresh: bool,
+
+    /// A filter expression over aircraft fields (`country`, `model`, `type_designator`,
+    /// `tail_number`, `icao_number`), e.g. `"country = 'Denmark' AND type_designator IN
+    /// ['F2TH','FA7X']"`. See [`flights::filter`] for the full grammar.
+    #[arg(long)]
+    filter: Option<String>,
 }
 
 #[tokio::main(flavor = "multi_thread")]
@@ -73,12 +86,14 @@ async fn main() -> Result<(), Box<dyn Error>> {
     // load datasets to memory
     let date = time::OffsetDateTime::now_utc().date();
     let aircrafts = aircraft::read(date, client).await?;
-    let models = load_private_jet_models()?;
+    let models = load_private_jet_models(cli.refresh)?;
+    let matches_filter = cli.filter.as_deref().map(filter::parse).transpose()?;
 
     let private_jets = aircrafts
         .values()
         // its primary use is to be a private jet
-        .filter(|a| models.contains_key(&a.model));
+        .filter(|a| models.contains_key(&a.model))
+        .filter(|a| matches_filter.as_ref().map_or(true, |f| f(a)));
 
     let data_csv = flights::csv::serialize(private_jets);
 
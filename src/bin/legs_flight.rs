@@ -0,0 +1,355 @@
+//! Serves the legs dataset over Arrow Flight, so an analyst can stream a filtered, interactive
+//! slice of the S3-backed dataset with any Flight client instead of downloading the whole
+//! `all.csv`/`all.parquet` rollup.
+//!
+//! Only `get_flight_info`/`do_get` are implemented: a client calls `get_flight_info` with a
+//! [`LegFilter`] JSON-encoded as the [`FlightDescriptor`]'s `cmd`, gets back a single
+//! [`FlightEndpoint`] whose ticket carries that same filter, and calls `do_get` with it to stream
+//! the matching rows.
+use std::error::Error;
+use std::pin::Pin;
+use std::sync::Arc;
+
+use arrow::array::{ArrayRef, Float64Array, StringArray};
+use arrow::datatypes::{DataType, Field, Schema};
+use arrow::record_batch::RecordBatch;
+use arrow_flight::encode::FlightDataEncoderBuilder;
+use arrow_flight::flight_service_server::{FlightService, FlightServiceServer};
+use arrow_flight::{
+    Action, ActionType, Criteria, Empty, FlightData, FlightDescriptor, FlightEndpoint, FlightInfo,
+    HandshakeRequest, HandshakeResponse, PutResult, SchemaAsIpc, SchemaResult, Ticket,
+};
+use clap::Parser;
+use futures::{Stream, StreamExt};
+use simple_logger::SimpleLogger;
+use tonic::{transport::Server, Request, Response, Status, Streaming};
+
+use flights::{
+    leg_co2e_kg_from_profile, load_private_jet_models, AircraftModels, BlobStorageProvider, Leg,
+    LocalDisk,
+};
+
+/// The filter carried by a Flight ticket (and round-tripped through a `FlightDescriptor`'s
+/// `cmd`), scoping which legs [`LegsFlightService::query`] streams back. All fields are
+/// optional; an empty filter matches every leg in the dataset.
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+struct LegFilter {
+    tail_number: Option<String>,
+    country: Option<String>,
+    /// Inclusive, in `yyyy-mm` form, e.g. `"2023-01"`.
+    from_month: Option<String>,
+    /// Exclusive, in `yyyy-mm` form.
+    to_month: Option<String>,
+    min_emissions_kg: Option<f64>,
+}
+
+impl LegFilter {
+    /// `from_month`/`to_month` only ever reach [`flights::serde::parse_month`] (which panics on a
+    /// malformed month) inside [`LegsFlightService::query`] - validate them here instead, while
+    /// we're still on the `Status`-returning side of the request, so a bad client-supplied month
+    /// is a clean `invalid_argument` rather than a panicked task.
+    fn validate_months(self) -> Result<Self, Status> {
+        for month in [&self.from_month, &self.to_month].into_iter().flatten() {
+            flights::serde::try_parse_month(month)
+                .map_err(|err| Status::invalid_argument(format!("invalid month: {err}")))?;
+        }
+        Ok(self)
+    }
+
+    fn from_descriptor(descriptor: &FlightDescriptor) -> Result<Self, Status> {
+        if descriptor.cmd.is_empty() {
+            return Ok(Self::default());
+        }
+        let filter: Self = serde_json::from_slice(&descriptor.cmd)
+            .map_err(|err| Status::invalid_argument(format!("invalid filter: {err}")))?;
+        filter.validate_months()
+    }
+
+    fn from_ticket(ticket: &Ticket) -> Result<Self, Status> {
+        if ticket.ticket.is_empty() {
+            return Ok(Self::default());
+        }
+        let filter: Self = serde_json::from_slice(&ticket.ticket)
+            .map_err(|err| Status::invalid_argument(format!("invalid ticket: {err}")))?;
+        filter.validate_months()
+    }
+
+    fn to_ticket(&self) -> Result<Ticket, Status> {
+        let bytes = serde_json::to_vec(self).map_err(|err| Status::internal(err.to_string()))?;
+        Ok(Ticket { ticket: bytes.into() })
+    }
+}
+
+/// One matched leg, flattened to the columns of [`row_schema`].
+struct Row {
+    icao_number: Arc<str>,
+    tail_number: String,
+    aircraft_model: String,
+    leg: Leg,
+    emissions_kg: f64,
+}
+
+/// The schema streamed by `do_get`: a deliberately small projection of `etl_legs`'s `LegOut`,
+/// enough for an analyst to answer a filtered question interactively.
+fn row_schema() -> Schema {
+    Schema::new(vec![
+        Field::new("icao_number", DataType::Utf8, false),
+        Field::new("tail_number", DataType::Utf8, false),
+        Field::new("aircraft_model", DataType::Utf8, false),
+        Field::new("start", DataType::Utf8, false),
+        Field::new("end", DataType::Utf8, false),
+        Field::new("distance_km", DataType::Float64, false),
+        Field::new("duration_hours", DataType::Float64, false),
+        Field::new("emissions_kg", DataType::Float64, false),
+    ])
+}
+
+fn rows_to_record_batch(rows: &[Row]) -> Result<RecordBatch, Box<dyn Error>> {
+    let rfc3339 = time::format_description::well_known::Rfc3339;
+    let columns: Vec<ArrayRef> = vec![
+        Arc::new(StringArray::from_iter_values(
+            rows.iter().map(|r| r.icao_number.as_ref()),
+        )),
+        Arc::new(StringArray::from_iter_values(
+            rows.iter().map(|r| r.tail_number.as_str()),
+        )),
+        Arc::new(StringArray::from_iter_values(
+            rows.iter().map(|r| r.aircraft_model.as_str()),
+        )),
+        Arc::new(StringArray::from_iter_values(rows.iter().map(|r| {
+            r.leg.from().datetime().format(&rfc3339).expect("valid timestamp")
+        }))),
+        Arc::new(StringArray::from_iter_values(rows.iter().map(|r| {
+            r.leg.to().datetime().format(&rfc3339).expect("valid timestamp")
+        }))),
+        Arc::new(Float64Array::from_iter_values(rows.iter().map(|r| r.leg.distance()))),
+        Arc::new(Float64Array::from_iter_values(
+            rows.iter().map(|r| r.leg.duration().as_seconds_f64() / 3600.0),
+        )),
+        Arc::new(Float64Array::from_iter_values(rows.iter().map(|r| r.emissions_kg))),
+    ];
+    Ok(RecordBatch::try_new(Arc::new(row_schema()), columns)?)
+}
+
+struct LegsFlightService {
+    client: Arc<dyn BlobStorageProvider + Send + Sync>,
+    models: Arc<AircraftModels>,
+}
+
+impl LegsFlightService {
+    /// Reuses [`flights::private_jets_in_month`] (tail/country/month-range scoped) to find the
+    /// required `(icao, month)`s, then the already-cached [`flights::get_month_positions`] for
+    /// each one -- the same loading path `etl_legs` uses, just queried on demand instead of
+    /// rebuilding the whole dataset.
+    async fn query(&self, filter: &LegFilter) -> Result<RecordBatch, Box<dyn Error>> {
+        let now = time::OffsetDateTime::now_utc().date();
+        let from_month = filter.from_month.as_deref().map(flights::serde::parse_month);
+        let to_month = filter.to_month.as_deref().map(flights::serde::parse_month);
+
+        let from_year = from_month.map(|d| d.year()).unwrap_or(2019);
+        let to_year = to_month.map(|d| d.year()).unwrap_or_else(|| now.year());
+
+        let jets_filter = flights::Filter {
+            countries: filter.country.iter().cloned().collect(),
+            ..Default::default()
+        };
+        let required =
+            flights::private_jets_in_month(from_year..=to_year, &jets_filter, self.client.as_ref())
+                .await?;
+
+        let mut rows = Vec::new();
+        for ((icao, month), (aircraft, _)) in &required {
+            if from_month.is_some_and(|from| *month < from) {
+                continue;
+            }
+            if to_month.is_some_and(|to| *month >= to) {
+                continue;
+            }
+            if let Some(tail) = &filter.tail_number {
+                if &aircraft.tail_number != tail {
+                    continue;
+                }
+            }
+            let Some(model) = self.models.get(&aircraft.model) else {
+                continue;
+            };
+
+            let Ok(positions) = flights::get_month_positions(icao, *month, self.client.as_ref()).await else {
+                continue; // this (icao, month) has not been crawled into the cache yet
+            };
+
+            for leg in flights::legs(positions.into_iter()) {
+                let emissions_kg = leg_co2e_kg_from_profile(model.gph as f64, &leg);
+                if filter.min_emissions_kg.is_some_and(|min| emissions_kg < min) {
+                    continue;
+                }
+                rows.push(Row {
+                    icao_number: icao.clone(),
+                    tail_number: aircraft.tail_number.clone(),
+                    aircraft_model: aircraft.model.clone(),
+                    leg,
+                    emissions_kg,
+                });
+            }
+        }
+        rows_to_record_batch(&rows)
+    }
+}
+
+type BoxStream<T> = Pin<Box<dyn Stream<Item = Result<T, Status>> + Send>>;
+
+#[tonic::async_trait]
+impl FlightService for LegsFlightService {
+    type HandshakeStream = BoxStream<HandshakeResponse>;
+    type ListFlightsStream = BoxStream<FlightInfo>;
+    type DoGetStream = BoxStream<FlightData>;
+    type DoPutStream = BoxStream<PutResult>;
+    type DoActionStream = BoxStream<arrow_flight::Result>;
+    type ListActionsStream = BoxStream<ActionType>;
+    type DoExchangeStream = BoxStream<FlightData>;
+
+    async fn handshake(
+        &self,
+        _request: Request<Streaming<HandshakeRequest>>,
+    ) -> Result<Response<Self::HandshakeStream>, Status> {
+        Err(Status::unimplemented("this server does not require authentication"))
+    }
+
+    async fn list_flights(
+        &self,
+        _request: Request<Criteria>,
+    ) -> Result<Response<Self::ListFlightsStream>, Status> {
+        Err(Status::unimplemented("the legs dataset is reached via get_flight_info, not list_flights"))
+    }
+
+    async fn get_flight_info(
+        &self,
+        request: Request<FlightDescriptor>,
+    ) -> Result<Response<FlightInfo>, Status> {
+        let descriptor = request.into_inner();
+        let filter = LegFilter::from_descriptor(&descriptor)?;
+
+        let options = arrow_ipc::writer::IpcWriteOptions::default();
+        let schema_ipc = SchemaAsIpc::new(&row_schema(), &options);
+        let schema_data: FlightData = schema_ipc
+            .try_into()
+            .map_err(|err: arrow::error::ArrowError| Status::internal(err.to_string()))?;
+
+        let endpoint = FlightEndpoint {
+            ticket: Some(filter.to_ticket()?),
+            location: vec![],
+            expiration_time: None,
+            app_metadata: Default::default(),
+        };
+
+        Ok(Response::new(FlightInfo {
+            schema: schema_data.data_header,
+            flight_descriptor: Some(descriptor),
+            endpoint: vec![endpoint],
+            total_records: -1,
+            total_bytes: -1,
+            ordered: false,
+            app_metadata: Default::default(),
+        }))
+    }
+
+    async fn get_schema(
+        &self,
+        _request: Request<FlightDescriptor>,
+    ) -> Result<Response<SchemaResult>, Status> {
+        Err(Status::unimplemented("call get_flight_info instead"))
+    }
+
+    async fn do_get(&self, request: Request<Ticket>) -> Result<Response<Self::DoGetStream>, Status> {
+        let filter = LegFilter::from_ticket(&request.into_inner())?;
+        let batch = self
+            .query(&filter)
+            .await
+            .map_err(|err| Status::internal(err.to_string()))?;
+
+        let stream = FlightDataEncoderBuilder::new()
+            .build(futures::stream::once(async move { Ok(batch) }))
+            .map(|result| result.map_err(|err| Status::internal(err.to_string())));
+
+        Ok(Response::new(Box::pin(stream)))
+    }
+
+    async fn do_put(
+        &self,
+        _request: Request<Streaming<FlightData>>,
+    ) -> Result<Response<Self::DoPutStream>, Status> {
+        Err(Status::unimplemented("the legs dataset is read-only"))
+    }
+
+    async fn do_action(&self, _request: Request<Action>) -> Result<Response<Self::DoActionStream>, Status> {
+        Err(Status::unimplemented("no actions are supported"))
+    }
+
+    async fn list_actions(
+        &self,
+        _request: Request<Empty>,
+    ) -> Result<Response<Self::ListActionsStream>, Status> {
+        Err(Status::unimplemented("no actions are supported"))
+    }
+
+    async fn do_exchange(
+        &self,
+        _request: Request<Streaming<FlightData>>,
+    ) -> Result<Response<Self::DoExchangeStream>, Status> {
+        Err(Status::unimplemented("do_exchange is not supported"))
+    }
+}
+
+#[derive(clap::ValueEnum, Debug, Clone, Copy)]
+enum Backend {
+    Disk,
+    Remote,
+}
+
+#[derive(Parser, Debug)]
+#[command(author, version, about = "Serves the leg database over Arrow Flight")]
+struct Cli {
+    /// The token to the remote storage
+    #[arg(long)]
+    access_key: Option<String>,
+    /// The token to the remote storage
+    #[arg(long)]
+    secret_access_key: Option<String>,
+    #[arg(long, value_enum, default_value_t=Backend::Remote)]
+    backend: Backend,
+
+    /// Address to listen on
+    #[arg(long, default_value = "0.0.0.0:50051")]
+    addr: String,
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn Error>> {
+    SimpleLogger::new()
+        .with_level(log::LevelFilter::Info)
+        .init()
+        .unwrap();
+
+    let cli = Cli::parse();
+
+    let client: Arc<dyn BlobStorageProvider + Send + Sync> = match cli.backend {
+        Backend::Disk => Arc::new(LocalDisk),
+        Backend::Remote => match (cli.access_key, cli.secret_access_key) {
+            (Some(access_key), Some(secret_access_key)) => {
+                Arc::new(flights::fs_s3::client(access_key, secret_access_key).await)
+            }
+            _ => Arc::new(flights::fs_s3::anonymous_client().await),
+        },
+    };
+    let models = Arc::new(load_private_jet_models(false)?);
+
+    let addr = cli.addr.parse()?;
+    log::info!("serving the legs dataset over Arrow Flight at {addr}");
+
+    Server::builder()
+        .add_service(FlightServiceServer::new(LegsFlightService { client, models }))
+        .serve(addr)
+        .await?;
+
+    Ok(())
+}
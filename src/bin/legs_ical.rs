@@ -0,0 +1,132 @@
+//! Exports a single aircraft's legs as an iCalendar (`.ics`) feed, so a journalist or activist
+//! can subscribe to a jet's movements in any calendar client instead of parsing CSV.
+use std::error::Error;
+
+use clap::Parser;
+use simple_logger::SimpleLogger;
+use time::macros::date;
+
+use flights::ical::{to_ical, IcalLeg};
+use flights::{
+    aircraft, airports_cached, leg_co2e_kg_from_profile, load_private_jet_models,
+    BlobStorageProvider, LocalDisk,
+};
+
+/// How many times more CO2e a private jet emits per passenger than an equivalent commercial
+/// flight, per [transportenvironment.org](https://www.transportenvironment.org/discover/private-jets-can-the-super-rich-supercharge-zero-emission-aviation/)
+/// (private jets emit 5-14x times; 10x is used as a middle estimate).
+const COMMERCIAL_TO_PRIVATE_RATIO: f64 = 10.0;
+
+#[derive(clap::ValueEnum, Debug, Clone, Copy)]
+enum Backend {
+    Disk,
+    Remote,
+}
+
+fn parse_date(arg: &str) -> Result<time::Date, time::error::Parse> {
+    time::Date::parse(
+        arg,
+        time::macros::format_description!("[year]-[month]-[day]"),
+    )
+}
+
+#[derive(Parser, Debug)]
+#[command(author, version, about = "Exports an aircraft's legs as an iCalendar feed")]
+struct Cli {
+    /// The token to the remote storage
+    #[arg(long)]
+    access_key: Option<String>,
+    /// The token to the remote storage
+    #[arg(long)]
+    secret_access_key: Option<String>,
+    #[arg(long, value_enum, default_value_t=Backend::Remote)]
+    backend: Backend,
+
+    /// Tail number of the aircraft to export, e.g. `OY-ABC`. Used to look up the ICAO hex
+    /// address when `--icao-number` is not given
+    #[arg(long)]
+    tail_number: Option<String>,
+    /// ICAO hex address of the aircraft to export, e.g. `45860d`
+    #[arg(long)]
+    icao_number: Option<String>,
+
+    /// A date in format `yyyy-mm-dd`
+    #[arg(long, value_parser = parse_date)]
+    from: time::Date,
+    /// Optional end date in format `yyyy-mm-dd` (else it is to today)
+    #[arg(long, value_parser = parse_date)]
+    to: Option<time::Date>,
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn Error>> {
+    SimpleLogger::new()
+        .with_level(log::LevelFilter::Info)
+        .init()
+        .unwrap();
+
+    let cli = Cli::parse();
+
+    let client = match cli.backend {
+        Backend::Disk => None,
+        Backend::Remote => match (cli.access_key, cli.secret_access_key) {
+            (Some(access_key), Some(secret_access_key)) => {
+                Some(flights::fs_s3::client(access_key, secret_access_key).await)
+            }
+            _ => Some(flights::fs_s3::anonymous_client().await),
+        },
+    };
+    let client = client
+        .as_ref()
+        .map(|x| x as &dyn BlobStorageProvider)
+        .unwrap_or(&LocalDisk);
+
+    let aircrafts = aircraft::read(date!(2023 - 11 - 06), client).await?;
+    let (icao_number, aircraft) = aircrafts
+        .iter()
+        .find(|(icao, a)| {
+            cli.icao_number.as_deref() == Some(icao.as_ref())
+                || cli.tail_number.as_deref() == Some(a.tail_number.as_str())
+        })
+        .ok_or("no aircraft matching --icao-number/--tail-number was found")?;
+
+    let models = load_private_jet_models(false)?;
+    let model = models
+        .get(&aircraft.model)
+        .ok_or_else(|| format!("no emissions model found for {}", aircraft.model))?;
+
+    let now = time::OffsetDateTime::now_utc().date();
+    let to = cli.to.unwrap_or(now);
+
+    let airports = airports_cached().await?;
+
+    let positions = flights::aircraft_positions(cli.from, to, icao_number, client).await?;
+    let legs = flights::legs(positions.into_iter())
+        .map(|leg| {
+            let emissions_kg = leg_co2e_kg_from_profile(model.gph as f64, &leg);
+            IcalLeg {
+                start: leg.from().datetime(),
+                end: leg.to().datetime(),
+                start_lat: leg.from().latitude(),
+                start_lon: leg.from().longitude(),
+                end_lat: leg.to().latitude(),
+                end_lon: leg.to().longitude(),
+                distance_km: leg.distance(),
+                emissions_kg,
+                commercial_emissions_kg: emissions_kg / COMMERCIAL_TO_PRIVATE_RATIO,
+                from_airport: airports.label(leg.from().pos()),
+                to_airport: airports.label(leg.to().pos()),
+            }
+        })
+        .collect::<Vec<_>>();
+
+    log::info!("{} legs found for {icao_number}", legs.len());
+
+    let ical = to_ical(icao_number, &aircraft.tail_number, &aircraft.model, &legs);
+
+    let key = format!("leg/v2/ical/icao_number={icao_number}/calendar.ics");
+    client.put(&key, ical.into_bytes()).await?;
+    log::info!("Written {key}");
+
+    Ok(())
+}
@@ -0,0 +1,141 @@
+//! Exposes the `leg/v2/` database built by `etl_legs` as a queryable SQL table, so questions
+//! like "total CO2 per tail number in 2023" or "flights under 30 min" can be answered without
+//! writing Rust.
+use std::error::Error;
+use std::sync::Arc;
+
+use clap::Parser;
+use datafusion::prelude::{CsvReadOptions, ParquetReadOptions, SessionContext};
+use simple_logger::SimpleLogger;
+use url::Url;
+
+use flights::blob_object_store::BlobObjectStore;
+use flights::{BlobStorageProvider, LocalDisk};
+
+static DATABASE_ROOT: &'static str = "leg/v2/";
+/// The scheme under which the dataset's [`BlobObjectStore`] is registered with DataFusion; it
+/// does not correspond to a real network location, it is just a name for `ctx.register_object_store`.
+static STORE_URL: &'static str = "blob://legs";
+
+#[derive(clap::ValueEnum, Debug, Clone, Copy)]
+enum Backend {
+    Disk,
+    Remote,
+}
+
+/// The on-disk format of the `data.{csv,parquet}` files written by `etl_legs --format`; a given
+/// deployment of the dataset is consistently one or the other, so this picks which listing
+/// format to register the tables with.
+#[derive(clap::ValueEnum, Debug, Clone, Copy, Default)]
+enum DataFormat {
+    #[default]
+    Csv,
+    Parquet,
+}
+
+/// The format results are printed in.
+#[derive(clap::ValueEnum, Debug, Clone, Copy, Default)]
+enum OutputFormat {
+    #[default]
+    Csv,
+    Json,
+}
+
+#[derive(Parser, Debug)]
+#[command(author, version, about = "Runs a SQL query against the leg database")]
+struct Cli {
+    /// The token to the remote storage
+    #[arg(long)]
+    access_key: Option<String>,
+    /// The token to the remote storage
+    #[arg(long)]
+    secret_access_key: Option<String>,
+    #[arg(long, value_enum, default_value_t=Backend::Remote)]
+    backend: Backend,
+
+    /// The format the `leg/v2/` dataset was written in
+    #[arg(long, value_enum, default_value_t=DataFormat::Csv)]
+    data_format: DataFormat,
+
+    /// The format query results are printed in
+    #[arg(long, value_enum, default_value_t=OutputFormat::Csv)]
+    format: OutputFormat,
+
+    /// The SQL query to run. The per-icao-month files are registered as `legs_by_month` and the
+    /// yearly rollups as `legs`, both with the [`LegOut`](etl_legs) schema. No partition columns
+    /// are registered: the `month=`/`icao_number=`/`year=` path keys are not queryable columns
+    sql: String,
+}
+
+async fn register_tables(
+    ctx: &SessionContext,
+    data_format: DataFormat,
+) -> Result<(), Box<dyn Error>> {
+    let by_month = format!("{STORE_URL}/{DATABASE_ROOT}data/");
+    let rollups = format!("{STORE_URL}/{DATABASE_ROOT}all/");
+
+    match data_format {
+        DataFormat::Csv => {
+            let options = CsvReadOptions::new().has_header(true);
+            ctx.register_csv("legs_by_month", &by_month, options.clone())
+                .await?;
+            ctx.register_csv("legs", &rollups, options).await?;
+        }
+        DataFormat::Parquet => {
+            let options = ParquetReadOptions::default();
+            ctx.register_parquet("legs_by_month", &by_month, options.clone())
+                .await?;
+            ctx.register_parquet("legs", &rollups, options).await?;
+        }
+    }
+    Ok(())
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn Error>> {
+    SimpleLogger::new()
+        .with_level(log::LevelFilter::Info)
+        .init()
+        .unwrap();
+
+    let cli = Cli::parse();
+
+    let provider: Arc<dyn BlobStorageProvider + Send + Sync> = match cli.backend {
+        Backend::Disk => Arc::new(LocalDisk),
+        Backend::Remote => match (cli.access_key, cli.secret_access_key) {
+            (Some(access_key), Some(secret_access_key)) => {
+                Arc::new(flights::fs_s3::client(access_key, secret_access_key).await)
+            }
+            _ => Arc::new(flights::fs_s3::anonymous_client().await),
+        },
+    };
+
+    let ctx = SessionContext::new();
+    let store = BlobObjectStore::new(provider);
+    ctx.runtime_env()
+        .register_object_store(&Url::parse(STORE_URL)?, Arc::new(store));
+
+    register_tables(&ctx, cli.data_format).await?;
+
+    log::info!("Running: {}", cli.sql);
+    let batches = ctx.sql(&cli.sql).await?.collect().await?;
+
+    match cli.format {
+        OutputFormat::Csv => {
+            let mut writer = arrow::csv::Writer::new(std::io::stdout());
+            for batch in &batches {
+                writer.write(batch)?;
+            }
+        }
+        OutputFormat::Json => {
+            let mut writer = arrow::json::ArrayWriter::new(Vec::new());
+            for batch in &batches {
+                writer.write(batch)?;
+            }
+            writer.finish()?;
+            print!("{}", String::from_utf8(writer.into_inner())?);
+        }
+    }
+
+    Ok(())
+}
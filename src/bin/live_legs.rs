@@ -0,0 +1,111 @@
+//! Tails current private-jet activity over a chosen area: polls the live feed on a fixed
+//! interval and prints a leg the moment an aircraft inside the region transitions from flying to
+//! grounded. Runs until killed -- there is no natural end to a live feed.
+use std::error::Error;
+
+use clap::Parser;
+use futures::StreamExt;
+use simple_logger::SimpleLogger;
+
+use flights::{BlobStorageProvider, Region};
+
+#[derive(clap::ValueEnum, Debug, Clone, Copy)]
+enum Backend {
+    Disk,
+    Remote,
+}
+
+#[derive(Parser, Debug)]
+#[command(author, version, about = "Tails current private-jet activity over a region")]
+struct Cli {
+    /// The token to the remote storage
+    #[arg(long)]
+    access_key: Option<String>,
+    /// The token to the remote storage
+    #[arg(long)]
+    secret_access_key: Option<String>,
+    #[arg(long, value_enum, default_value_t=Backend::Remote)]
+    backend: Backend,
+    /// Skip appending every observed position to its day's rolling blob
+    #[arg(long)]
+    no_persist: bool,
+
+    /// Region's northern latitude bound, in degrees
+    #[arg(long)]
+    upper_lat: f64,
+    /// Region's southern latitude bound, in degrees
+    #[arg(long)]
+    lower_lat: f64,
+    /// Region's eastern longitude bound, in degrees
+    #[arg(long)]
+    upper_lon: f64,
+    /// Region's western longitude bound, in degrees
+    #[arg(long)]
+    lower_lon: f64,
+    /// Altitude floor in feet, below which a position is outside the region
+    #[arg(long)]
+    floor: Option<f64>,
+    /// Altitude ceiling in feet, above which a position is outside the region
+    #[arg(long)]
+    ceiling: Option<f64>,
+
+    /// Seconds to wait between polls of the live feed
+    #[arg(long, default_value_t = 15)]
+    interval_seconds: u64,
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn Error>> {
+    SimpleLogger::new()
+        .with_level(log::LevelFilter::Info)
+        .init()
+        .unwrap();
+
+    let cli = Cli::parse();
+
+    let client = match cli.backend {
+        Backend::Disk => None,
+        Backend::Remote => match (cli.access_key, cli.secret_access_key) {
+            (Some(access_key), Some(secret_access_key)) => {
+                Some(flights::fs_s3::client(access_key, secret_access_key).await)
+            }
+            _ => Some(flights::fs_s3::anonymous_client().await),
+        },
+    };
+    let client = client
+        .as_ref()
+        .map(|x| x as &dyn BlobStorageProvider)
+        .unwrap_or(&flights::LocalDisk);
+    let client = (!cli.no_persist).then_some(client);
+
+    let region = Region {
+        upper_lat: cli.upper_lat,
+        lower_lat: cli.lower_lat,
+        upper_lon: cli.upper_lon,
+        lower_lon: cli.lower_lon,
+        floor: cli.floor,
+        ceiling: cli.ceiling,
+    };
+    let interval = std::time::Duration::from_secs(cli.interval_seconds);
+
+    log::info!("tailing {region:?} every {interval:?}");
+    let mut legs = flights::live_legs(region, interval, client);
+    while let Some(result) = legs.next().await {
+        match result {
+            Ok((icao, leg)) => println!(
+                "{icao} landed: {} ({:.4}, {:.4}) -> {} ({:.4}, {:.4}), {:.1} km, {:.2}h",
+                leg.from().datetime(),
+                leg.from().latitude(),
+                leg.from().longitude(),
+                leg.to().datetime(),
+                leg.to().latitude(),
+                leg.to().longitude(),
+                leg.distance(),
+                leg.duration().as_seconds_f64() / 60.0 / 60.0,
+            ),
+            Err(err) => log::error!("{err}"),
+        }
+    }
+
+    Ok(())
+}
@@ -76,6 +76,11 @@ struct Cli {
     /// Optional end date in format `yyyy-mm-dd` (else it is to today)
     #[arg(long, value_parser = parse_date)]
     to: Option<time::Date>,
+
+    /// Force a rebuild of the local binary caches (owners, aircraft owners) instead of
+    /// reusing them
+    #[arg(long)]
+    refresh: bool,
 }
 
 #[tokio::main]
@@ -101,8 +106,8 @@ async fn main() -> Result<(), Box<dyn Error>> {
         .unwrap_or(&LocalDisk);
 
     // load datasets to memory
-    let owners = load_owners()?;
-    let aircraft_owners = load_aircraft_owners()?;
+    let owners = load_owners(cli.refresh)?;
+    let aircraft_owners = load_aircraft_owners(cli.refresh)?;
     let aircrafts = aircraft::read(date!(2023 - 11 - 06), client).await?;
 
     let from = cli.from;
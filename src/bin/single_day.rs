@@ -69,6 +69,10 @@ struct Cli {
     /// The backend to read cached data from.
     #[arg(short, long, value_enum, default_value_t=Backend::Remote)]
     backend: Backend,
+    /// Force a rebuild of the local binary caches (owners, aircraft owners, private
+    /// jet models) instead of reusing them
+    #[arg(long)]
+    refresh: bool,
 }
 
 fn parse_date(arg: &str) -> Result<time::Date, time::error::Parse> {
@@ -85,6 +89,7 @@ async fn flight_date(
     aircraft_owners: &AircraftOwners,
     aircrafts: &Aircrafts,
     client: &dyn BlobStorageProvider,
+    refresh: bool,
 ) -> Result<Vec<Event>, Box<dyn Error>> {
     let aircraft = aircrafts
         .get(tail_number)
@@ -97,7 +102,7 @@ async fn flight_date(
 
     log::info!("Number of legs: {}", legs.len());
 
-    let models = load_private_jet_models()?;
+    let models = load_private_jet_models(refresh)?;
     let airports = airports_cached().await?;
     let aircraft_owner = aircraft_owners
         .get(tail_number)
@@ -125,7 +130,7 @@ async fn flight_date(
             date: "2023-10-19".to_string()
         };
         let emissions_kg = Fact {
-            claim: leg_per_person(leg_co2e_kg(consumption.gph as f64, leg.duration())) as usize,
+            claim: leg_per_person(leg_co2e_kg_from_profile(consumption.gph as f64, &leg)) as usize,
             source: "See [methodology M-7](https://github.com/jorgecardleitao/private-jets/blob/main/methodology.md)".to_string(),
             date: time::OffsetDateTime::now_utc().date().to_string(),
         };
@@ -134,8 +139,8 @@ async fn flight_date(
             tail_number: tail_number.to_string(),
             owner: owner.clone(),
             date: date.to_string(),
-            from_airport: closest(leg.from().pos(), &airports).name.clone(),
-            to_airport: closest(leg.to().pos(), &airports).name.clone(),
+            from_airport: airports.label(leg.from().pos()),
+            to_airport: airports.label(leg.to().pos()),
             two_way: false,
             commercial_emissions_kg,
             emissions_kg,
@@ -197,8 +202,8 @@ async fn main() -> Result<(), Box<dyn Error>> {
         .map(|x| x as &dyn BlobStorageProvider)
         .unwrap_or(&LocalDisk);
 
-    let owners = load_owners()?;
-    let aircraft_owners = load_aircraft_owners()?;
+    let owners = load_owners(cli.refresh)?;
+    let aircraft_owners = load_aircraft_owners(cli.refresh)?;
     let aircrafts = aircraft::read(date!(2023 - 11 - 06), client).await?;
 
     let dane_emissions_kg = Fact {
@@ -214,6 +219,7 @@ async fn main() -> Result<(), Box<dyn Error>> {
         &aircraft_owners,
         &aircrafts,
         client,
+        cli.refresh,
     )
     .await?;
 
@@ -0,0 +1,184 @@
+//! Bridges [`BlobStorageProvider`] into [`object_store::ObjectStore`], the trait DataFusion's
+//! `ListingTable` reads through, so the `leg/v2/` dataset (local disk or the S3/DigitalOcean
+//! Spaces client) can be registered as a SQL table without a second storage implementation.
+use std::fmt::{Debug, Display, Formatter};
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use bytes::Bytes;
+use futures::stream::{self, BoxStream};
+use futures::TryStreamExt;
+use object_store::path::Path;
+use object_store::{
+    GetOptions, GetResult, GetResultPayload, ListResult, MultipartUpload, ObjectMeta, ObjectStore,
+    PutOptions, PutPayload, PutResult, Result as ObjectStoreResult,
+};
+
+use crate::fs::BlobStorageProvider;
+
+/// Adapts a [`BlobStorageProvider`] into an [`ObjectStore`]. `BlobStorageProvider` has no
+/// metadata-only HEAD call, so sizes are obtained by fetching each blob's contents; fine for the
+/// one-shot, read-mostly analytics queries this is built for, but not something to use for a
+/// hot path.
+pub struct BlobObjectStore {
+    provider: Arc<dyn BlobStorageProvider + Send + Sync>,
+}
+
+impl BlobObjectStore {
+    pub fn new(provider: Arc<dyn BlobStorageProvider + Send + Sync>) -> Self {
+        Self { provider }
+    }
+}
+
+impl Debug for BlobObjectStore {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("BlobObjectStore").finish()
+    }
+}
+
+impl Display for BlobObjectStore {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "BlobObjectStore")
+    }
+}
+
+fn wrap_err(source: std::io::Error) -> object_store::Error {
+    object_store::Error::Generic {
+        store: "BlobObjectStore",
+        source: Box::new(source),
+    }
+}
+
+fn not_found(location: &Path) -> object_store::Error {
+    object_store::Error::NotFound {
+        path: location.to_string(),
+        source: "blob not found".into(),
+    }
+}
+
+fn to_meta(key: String, data: &[u8]) -> ObjectMeta {
+    ObjectMeta {
+        location: Path::from(key),
+        last_modified: time::OffsetDateTime::now_utc().into(),
+        size: data.len(),
+        e_tag: None,
+        version: None,
+    }
+}
+
+#[async_trait]
+impl ObjectStore for BlobObjectStore {
+    async fn put_opts(
+        &self,
+        location: &Path,
+        payload: PutPayload,
+        _opts: PutOptions,
+    ) -> ObjectStoreResult<PutResult> {
+        let bytes: Bytes = payload.into();
+        self.provider
+            .put(location.as_ref(), bytes.to_vec())
+            .await
+            .map_err(wrap_err)?;
+        Ok(PutResult {
+            e_tag: None,
+            version: None,
+        })
+    }
+
+    async fn put_multipart(&self, _location: &Path) -> ObjectStoreResult<Box<dyn MultipartUpload>> {
+        Err(object_store::Error::NotImplemented)
+    }
+
+    async fn get_opts(&self, location: &Path, _options: GetOptions) -> ObjectStoreResult<GetResult> {
+        let data = self
+            .provider
+            .maybe_get(location.as_ref())
+            .await
+            .map_err(wrap_err)?
+            .ok_or_else(|| not_found(location))?;
+
+        let meta = to_meta(location.to_string(), &data);
+        let bytes = Bytes::from(data);
+        let range = 0..bytes.len();
+        Ok(GetResult {
+            payload: GetResultPayload::Stream(Box::pin(stream::once(async move { Ok(bytes) }))),
+            meta,
+            range,
+            attributes: Default::default(),
+        })
+    }
+
+    async fn head(&self, location: &Path) -> ObjectStoreResult<ObjectMeta> {
+        let data = self
+            .provider
+            .maybe_get(location.as_ref())
+            .await
+            .map_err(wrap_err)?
+            .ok_or_else(|| not_found(location))?;
+        Ok(to_meta(location.to_string(), &data))
+    }
+
+    async fn delete(&self, location: &Path) -> ObjectStoreResult<()> {
+        self.provider.delete(location.as_ref()).await.map_err(wrap_err)
+    }
+
+    fn list(&self, prefix: Option<&Path>) -> BoxStream<'_, ObjectStoreResult<ObjectMeta>> {
+        let prefix_str = prefix.map(|p| p.to_string()).unwrap_or_default();
+        let provider = Arc::clone(&self.provider);
+
+        let stream = stream::once(async move {
+            let keys = provider.list(&prefix_str).await.map_err(wrap_err)?;
+            let mut metas = Vec::with_capacity(keys.len());
+            for key in keys {
+                if let Some(data) = provider.maybe_get(&key).await.map_err(wrap_err)? {
+                    metas.push(Ok(to_meta(key, &data)));
+                }
+            }
+            Ok::<_, object_store::Error>(stream::iter(metas))
+        })
+        .try_flatten();
+
+        Box::pin(stream)
+    }
+
+    /// Groups `list`'s flat key space by the segment following `prefix`, since the leg
+    /// database's `month=.../icao_number=.../data.*` layout has no native directory listing.
+    async fn list_with_delimiter(&self, prefix: Option<&Path>) -> ObjectStoreResult<ListResult> {
+        let prefix_str = prefix.map(|p| p.to_string()).unwrap_or_default();
+        let keys = self.provider.list(&prefix_str).await.map_err(wrap_err)?;
+
+        let mut objects = Vec::new();
+        let mut common_prefixes = std::collections::BTreeSet::new();
+        for key in keys {
+            let rest = key.strip_prefix(&prefix_str).unwrap_or(&key).trim_start_matches('/');
+            match rest.split_once('/') {
+                Some((dir, _)) => {
+                    let mut p = prefix_str.clone();
+                    if !p.is_empty() && !p.ends_with('/') {
+                        p.push('/');
+                    }
+                    p.push_str(dir);
+                    common_prefixes.insert(Path::from(p));
+                }
+                None => {
+                    if let Some(data) = self.provider.maybe_get(&key).await.map_err(wrap_err)? {
+                        objects.push(to_meta(key, &data));
+                    }
+                }
+            }
+        }
+
+        Ok(ListResult {
+            objects,
+            common_prefixes: common_prefixes.into_iter().collect(),
+        })
+    }
+
+    async fn copy(&self, _from: &Path, _to: &Path) -> ObjectStoreResult<()> {
+        Err(object_store::Error::NotImplemented)
+    }
+
+    async fn copy_if_not_exists(&self, _from: &Path, _to: &Path) -> ObjectStoreResult<()> {
+        Err(object_store::Error::NotImplemented)
+    }
+}
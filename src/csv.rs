@@ -34,3 +34,39 @@ pub fn deserialize<'a, D: serde::de::DeserializeOwned + 'a>(
         record
     })
 }
+
+/// Serializes `items` as NDJSON: one `serde_json` object per line.
+pub fn serialize_ndjson(items: impl Iterator<Item = impl serde::Serialize>) -> Vec<u8> {
+    let mut out = Vec::new();
+    for item in items {
+        serde_json::to_writer(&mut out, &item).unwrap();
+        out.push(b'\n');
+    }
+    out
+}
+
+/// Parses NDJSON `data`: one `serde_json` object per non-empty trimmed line.
+/// Unlike [`deserialize`], parse errors are surfaced (with the offending line number, or as a
+/// single item if `data` isn't valid UTF-8) instead of panicking, since NDJSON is commonly
+/// produced by untrusted downstream tools.
+pub fn deserialize_ndjson<'a, D: serde::de::DeserializeOwned + 'a>(
+    data: &'a [u8],
+) -> impl Iterator<Item = Result<D, Box<dyn Error>>> + 'a {
+    let text = match std::str::from_utf8(data) {
+        Ok(text) => text,
+        Err(e) => {
+            let err: Box<dyn Error> = format!("invalid utf-8: {e}").into();
+            return vec![Err(err)].into_iter();
+        }
+    };
+
+    text.lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(number, line)| {
+            serde_json::from_str(line.trim())
+                .map_err(|e| -> Box<dyn Error> { format!("line {}: {e}", number + 1).into() })
+        })
+        .collect::<Vec<_>>()
+        .into_iter()
+}
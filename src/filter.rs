@@ -0,0 +1,288 @@
+//! A small filter-expression DSL for selecting [`Aircraft`] rows ad-hoc (e.g. from a CLI flag),
+//! instead of hard-coding a predicate in code: `country = 'Denmark' AND type_designator IN
+//! ['F2TH','FA7X']`. [`parse`] tokenizes the expression (splitting on whitespace, keeping quoted
+//! string literals intact), runs a recursive-descent parser over comparisons (`=`, `!=`, `>`,
+//! `<`, `>=`, `<=`, and an `IN [a, b, c]` set-membership form) combined with `AND`/`OR` and
+//! parenthesized grouping (`OR` binds loosest, then `AND`, then comparisons), and compiles the
+//! resulting AST into a single `Fn(&Aircraft) -> bool` predicate.
+use crate::aircraft::{Aircraft, COLUMNS};
+
+/// A malformed filter expression, naming the offending token (or `<end of input>`).
+#[derive(Debug, PartialEq)]
+pub struct ParseError(String);
+
+impl std::fmt::Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "invalid filter expression near {:?}", self.0)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Op {
+    Eq,
+    Ne,
+    Gt,
+    Lt,
+    Ge,
+    Le,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Expr {
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    Cmp(String, Op, String),
+    In(String, Vec<String>),
+}
+
+impl Expr {
+    fn eval(&self, aircraft: &Aircraft) -> bool {
+        match self {
+            Expr::And(left, right) => left.eval(aircraft) && right.eval(aircraft),
+            Expr::Or(left, right) => left.eval(aircraft) || right.eval(aircraft),
+            Expr::Cmp(field, op, value) => {
+                let actual = aircraft.column(field);
+                match op {
+                    Op::Eq => actual == value,
+                    Op::Ne => actual != value,
+                    Op::Gt => actual > value.as_str(),
+                    Op::Lt => actual < value.as_str(),
+                    Op::Ge => actual >= value.as_str(),
+                    Op::Le => actual <= value.as_str(),
+                }
+            }
+            Expr::In(field, values) => {
+                let actual = aircraft.column(field);
+                values.iter().any(|value| value == actual)
+            }
+        }
+    }
+}
+
+/// Splits `input` into tokens: `(`, `)`, `[`, `]`, `,` and the comparison operators are their own
+/// tokens; `'...'`/`"..."` string literals are read to their closing quote (without the quotes)
+/// regardless of what they contain; everything else is a maximal run of non-whitespace,
+/// non-punctuation characters (a field name, bare value or `AND`/`OR`/`IN` keyword).
+fn tokenize(input: &str) -> Result<Vec<String>, ParseError> {
+    const PUNCTUATION: &str = "()[],='\"!<>";
+
+    let chars = input.chars().collect::<Vec<_>>();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        if c.is_whitespace() {
+            i += 1;
+        } else if "()[],".contains(c) {
+            tokens.push(c.to_string());
+            i += 1;
+        } else if c == '\'' || c == '"' {
+            let quote = c;
+            let start = i;
+            i += 1;
+            while i < chars.len() && chars[i] != quote {
+                i += 1;
+            }
+            if i >= chars.len() {
+                return Err(ParseError(chars[start..].iter().collect()));
+            }
+            tokens.push(chars[start + 1..i].iter().collect());
+            i += 1;
+        } else if "=!<>".contains(c) {
+            let start = i;
+            i += 1;
+            if i < chars.len() && chars[i] == '=' {
+                i += 1;
+            }
+            tokens.push(chars[start..i].iter().collect());
+        } else {
+            let start = i;
+            while i < chars.len() && !chars[i].is_whitespace() && !PUNCTUATION.contains(chars[i]) {
+                i += 1;
+            }
+            tokens.push(chars[start..i].iter().collect());
+        }
+    }
+    Ok(tokens)
+}
+
+struct Parser<'a> {
+    tokens: &'a [String],
+    position: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn peek(&self) -> Option<&'a str> {
+        self.tokens.get(self.position).map(String::as_str)
+    }
+
+    fn advance(&mut self) -> Result<&'a str, ParseError> {
+        let token = self
+            .tokens
+            .get(self.position)
+            .map(String::as_str)
+            .ok_or_else(|| ParseError("<end of input>".to_string()))?;
+        self.position += 1;
+        Ok(token)
+    }
+
+    fn expect(&mut self, expected: &str) -> Result<(), ParseError> {
+        let token = self.advance()?;
+        (token == expected)
+            .then_some(())
+            .ok_or_else(|| ParseError(token.to_string()))
+    }
+
+    /// `or := and ("OR" and)*`
+    fn or(&mut self) -> Result<Expr, ParseError> {
+        let mut left = self.and()?;
+        while self.peek() == Some("OR") {
+            self.position += 1;
+            let right = self.and()?;
+            left = Expr::Or(Box::new(left), Box::new(right));
+        }
+        Ok(left)
+    }
+
+    /// `and := unary ("AND" unary)*`
+    fn and(&mut self) -> Result<Expr, ParseError> {
+        let mut left = self.unary()?;
+        while self.peek() == Some("AND") {
+            self.position += 1;
+            let right = self.unary()?;
+            left = Expr::And(Box::new(left), Box::new(right));
+        }
+        Ok(left)
+    }
+
+    /// `unary := "(" or ")" | comparison`
+    fn unary(&mut self) -> Result<Expr, ParseError> {
+        if self.peek() == Some("(") {
+            self.position += 1;
+            let expr = self.or()?;
+            self.expect(")")?;
+            return Ok(expr);
+        }
+        self.comparison()
+    }
+
+    /// `comparison := field "IN" "[" value ("," value)* "]" | field OP value`
+    fn comparison(&mut self) -> Result<Expr, ParseError> {
+        let field = self.advance()?;
+        if !COLUMNS.contains(&field) {
+            return Err(ParseError(field.to_string()));
+        }
+        let field = field.to_string();
+
+        if self.peek() == Some("IN") {
+            self.position += 1;
+            self.expect("[")?;
+            let mut values = vec![self.advance()?.to_string()];
+            loop {
+                match self.advance()? {
+                    "," => values.push(self.advance()?.to_string()),
+                    "]" => break,
+                    other => return Err(ParseError(other.to_string())),
+                }
+            }
+            return Ok(Expr::In(field, values));
+        }
+
+        let op = match self.advance()? {
+            "=" => Op::Eq,
+            "!=" => Op::Ne,
+            ">" => Op::Gt,
+            "<" => Op::Lt,
+            ">=" => Op::Ge,
+            "<=" => Op::Le,
+            other => return Err(ParseError(other.to_string())),
+        };
+        let value = self.advance()?.to_string();
+        Ok(Expr::Cmp(field, op, value))
+    }
+}
+
+/// Parses `input` (see the [module docs](self)) into a predicate that can be applied to
+/// [`Aircraft`] values, e.g. in a `.filter(...)` step before [`crate::csv::serialize`].
+pub fn parse(input: &str) -> Result<impl Fn(&Aircraft) -> bool, ParseError> {
+    let tokens = tokenize(input)?;
+    let mut parser = Parser {
+        tokens: &tokens,
+        position: 0,
+    };
+    let expr = parser.or()?;
+    if parser.position != tokens.len() {
+        return Err(ParseError(tokens[parser.position].clone()));
+    }
+    Ok(move |aircraft: &Aircraft| expr.eval(aircraft))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn aircraft(country: &str, type_designator: &str) -> Aircraft {
+        Aircraft {
+            icao_number: "45860d".into(),
+            tail_number: "OY-GFS".to_string(),
+            type_designator: type_designator.to_string(),
+            model: "GLF6".to_string(),
+            country: Some(country.into()),
+        }
+    }
+
+    #[test]
+    fn equality() {
+        let predicate = parse("country = 'Denmark'").unwrap();
+        assert!(predicate(&aircraft("Denmark", "F2TH")));
+        assert!(!predicate(&aircraft("France", "F2TH")));
+    }
+
+    #[test]
+    fn in_set() {
+        let predicate = parse("type_designator IN ['F2TH', 'FA7X']").unwrap();
+        assert!(predicate(&aircraft("Denmark", "F2TH")));
+        assert!(predicate(&aircraft("Denmark", "FA7X")));
+        assert!(!predicate(&aircraft("Denmark", "GLF6")));
+    }
+
+    #[test]
+    fn and_or_precedence_and_grouping() {
+        // OR binds loosest: this reads as `(country = 'Denmark' AND type_designator = 'F2TH')
+        // OR type_designator = 'FA7X'`.
+        let predicate =
+            parse("country = 'Denmark' AND type_designator = 'F2TH' OR type_designator = 'FA7X'")
+                .unwrap();
+        assert!(predicate(&aircraft("France", "FA7X")));
+        assert!(!predicate(&aircraft("France", "F2TH")));
+
+        let grouped = parse(
+            "country = 'Denmark' AND (type_designator = 'F2TH' OR type_designator = 'FA7X')",
+        )
+        .unwrap();
+        assert!(!grouped(&aircraft("France", "FA7X")));
+        assert!(grouped(&aircraft("Denmark", "FA7X")));
+    }
+
+    #[test]
+    fn unknown_field_is_a_parse_error() {
+        assert_eq!(
+            parse("altitude = '100'").unwrap_err(),
+            ParseError("altitude".to_string())
+        );
+    }
+
+    #[test]
+    fn malformed_expression_names_the_offending_token() {
+        assert_eq!(
+            parse("country ~ 'Denmark'").unwrap_err(),
+            ParseError("~".to_string())
+        );
+        assert_eq!(
+            parse("country = 'Denmark' country").unwrap_err(),
+            ParseError("country".to_string())
+        );
+    }
+}
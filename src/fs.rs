@@ -1,18 +1,112 @@
+use std::hash::{Hash, Hasher};
 use std::path::{Path, PathBuf};
 
 use async_trait::async_trait;
+use futures::stream::BoxStream;
+use futures::StreamExt;
+use futures::TryStreamExt;
 
 static ROOT: &'static str = "database/";
 
+/// An opaque, provider-defined cursor returned by [`BlobStorageProvider::sync`]. Pass the token
+/// from a previous call back in as `since` to list only what changed after it, instead of
+/// re-listing (and re-reading) an entire prefix every run. Analogous to a WebDAV
+/// sync-collection `sync-token`.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct SyncToken(pub(crate) String);
+
+/// A blob that appeared or disappeared under a [`BlobStorageProvider::sync`]'d prefix since the
+/// token passed as `since`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Change {
+    Added(String),
+    Removed(String),
+}
+
 /// An object that can be used to get and put blobs.
 #[async_trait]
 pub trait BlobStorageProvider {
     async fn maybe_get(&self, blob_name: &str) -> Result<Option<Vec<u8>>, std::io::Error>;
     async fn put(&self, blob_name: &str, contents: Vec<u8>) -> Result<(), std::io::Error>;
-    async fn list(&self, prefix: &str) -> Result<Vec<String>, std::io::Error>;
     async fn delete(&self, blob_name: &str) -> Result<(), std::io::Error>;
 
+    /// Yields the keys under `prefix` page-by-page as the listing advances, instead of buffering
+    /// all of them before the caller sees any - a prefix like `leg/v1/all/` can hold hundreds of
+    /// thousands of keys. Lets a caller filter (e.g. by `year=`/`month=` path components) or
+    /// back-pressure without waiting for the whole listing to drain.
+    fn list_stream<'a>(&'a self, prefix: &'a str) -> BoxStream<'a, Result<String, std::io::Error>>;
+
+    /// Convenience over [`list_stream`] that drives it to completion and collects every key.
+    async fn list(&self, prefix: &str) -> Result<Vec<String>, std::io::Error> {
+        self.list_stream(prefix).try_collect().await
+    }
+
     fn can_put(&self) -> bool;
+
+    /// Lists the blobs under `prefix` that were added or removed since `since`, along with a new
+    /// [`SyncToken`] to pass to the next call. `since: None` reports every blob under `prefix` as
+    /// [`Change::Added`].
+    /// # Implementation
+    /// The default implementation has no incremental state to draw on, so it always performs a
+    /// full [`BlobStorageProvider::list`] and reports every result as `Added` (never `Removed`).
+    /// Providers that can track change order cheaply (mtimes, a continuation marker, ...) should
+    /// override it - see [`LocalDisk`] and `fs_s3::ContainerClient`.
+    async fn sync(
+        &self,
+        prefix: &str,
+        _since: Option<SyncToken>,
+    ) -> Result<(Vec<Change>, SyncToken), std::io::Error> {
+        let blobs = self.list(prefix).await?;
+        let token = SyncToken(time::OffsetDateTime::now_utc().unix_timestamp().to_string());
+        Ok((blobs.into_iter().map(Change::Added).collect(), token))
+    }
+}
+
+/// A provider-defined cursor shared by [`LocalDisk`] and `fs_s3::ContainerClient`'s
+/// [`BlobStorageProvider::sync`] overrides: the set of blobs known as of `as_of_unix`, so that a
+/// blob present before but missing now can be reported as [`Change::Removed`].
+#[derive(Debug, Default, serde::Serialize, serde::Deserialize)]
+pub(crate) struct SyncCursor {
+    pub(crate) as_of_unix: i64,
+    pub(crate) known: std::collections::BTreeSet<String>,
+}
+
+impl SyncCursor {
+    pub(crate) fn decode(token: Option<SyncToken>) -> Result<Self, std::io::Error> {
+        token
+            .map(|token| serde_json::from_str(&token.0).map_err(std::io::Error::other))
+            .transpose()
+            .map(Option::unwrap_or_default)
+    }
+
+    pub(crate) fn encode(&self) -> Result<SyncToken, std::io::Error> {
+        serde_json::to_string(self)
+            .map(SyncToken)
+            .map_err(std::io::Error::other)
+    }
+
+    /// Diffs `current` (every blob observed by this `sync` call, with its last-modified time as a
+    /// unix timestamp) against what was `known` as of the previous call, returning the changes and
+    /// the cursor to persist for the next one.
+    pub(crate) fn diff(
+        self,
+        current: impl IntoIterator<Item = (String, i64)>,
+    ) -> (Vec<Change>, SyncCursor) {
+        let mut changes = Vec::new();
+        let mut seen = std::collections::BTreeSet::new();
+        for (blob_name, modified) in current {
+            if modified > self.as_of_unix || !self.known.contains(&blob_name) {
+                changes.push(Change::Added(blob_name.clone()));
+            }
+            seen.insert(blob_name);
+        }
+        for removed in self.known.difference(&seen) {
+            changes.push(Change::Removed(removed.clone()));
+        }
+
+        let as_of_unix = time::OffsetDateTime::now_utc().unix_timestamp();
+        (changes, SyncCursor { as_of_unix, known: seen })
+    }
 }
 
 /// A [`BlobStorageProvider`] for local disk
@@ -40,19 +134,75 @@ impl BlobStorageProvider for LocalDisk {
         Ok(())
     }
 
-    #[must_use]
-    async fn list(&self, _prefix: &str) -> Result<Vec<String>, std::io::Error> {
-        todo!()
+    fn list_stream<'a>(&'a self, prefix: &'a str) -> BoxStream<'a, Result<String, std::io::Error>> {
+        let root = PathBuf::from(ROOT);
+        let mut entries = Vec::new();
+        let result = walk_mtimes(&root.join(Path::new(prefix)), &root, &mut entries);
+        futures::stream::iter(match result {
+            Ok(()) => entries.into_iter().map(|(blob_name, _)| Ok(blob_name)).collect(),
+            Err(err) => vec![Err(err)],
+        })
+        .boxed()
     }
 
     #[must_use]
-    async fn delete(&self, _prefix: &str) -> Result<(), std::io::Error> {
-        todo!()
+    async fn delete(&self, blob_name: &str) -> Result<(), std::io::Error> {
+        let path = PathBuf::from(ROOT).join(Path::new(blob_name));
+        match std::fs::remove_file(path) {
+            Ok(()) => Ok(()),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(()),
+            Err(err) => Err(err),
+        }
     }
 
     fn can_put(&self) -> bool {
         true
     }
+
+    async fn sync(
+        &self,
+        prefix: &str,
+        since: Option<SyncToken>,
+    ) -> Result<(Vec<Change>, SyncToken), std::io::Error> {
+        let previous = SyncCursor::decode(since)?;
+
+        let mut current = Vec::new();
+        let root = PathBuf::from(ROOT);
+        walk_mtimes(&root.join(Path::new(prefix)), &root, &mut current)?;
+
+        let (changes, cursor) = previous.diff(current);
+        Ok((changes, cursor.encode()?))
+    }
+}
+
+/// Recursively collects every file under `dir` as a `(blob_name, mtime)` pair, where `blob_name`
+/// is its path relative to `root` with `/` separators, for [`LocalDisk`]'s [`BlobStorageProvider::sync`].
+fn walk_mtimes(dir: &Path, root: &Path, out: &mut Vec<(String, i64)>) -> std::io::Result<()> {
+    if !dir.try_exists()? {
+        return Ok(());
+    }
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            walk_mtimes(&path, root, out)?;
+            continue;
+        }
+        let modified = path
+            .metadata()?
+            .modified()?
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs() as i64;
+        let blob_name = path
+            .strip_prefix(root)
+            .unwrap()
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/");
+        out.push((blob_name, modified));
+    }
+    Ok(())
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -71,7 +221,16 @@ impl CacheAction {
     }
 }
 
-/// Tries to retrive `blob_name` from `provider`. If it does not exist,
+/// Namespaces `blob_name` under its schema `version`, e.g. `aircrafts.bin` at version `3` becomes
+/// `v3/aircrafts.bin`. This lets a format change (a new `Position` field, a rewritten trace
+/// parser) invalidate every previously cached blob just by bumping `version`, instead of everyone
+/// who runs this crate having to manually wipe `database/`.
+pub(crate) fn versioned_blob_name(blob_name: &str, version: u32) -> String {
+    format!("v{version}/{blob_name}")
+}
+
+/// Tries to retrive `blob_name` from `provider`, namespaced under `version`. If it does not
+/// exist - either because it was never written, or because `version` was bumped since it was -
 /// it calls `fetch` and writes the result into `provider`.
 /// Returns the data in `blob_name` from `provider`.
 /// # Implementation
@@ -81,11 +240,13 @@ pub async fn cached<E, F>(
     fetch: F,
     provider: &dyn BlobStorageProvider,
     action: CacheAction,
+    version: u32,
 ) -> Result<Vec<u8>, std::io::Error>
 where
     E: std::error::Error + Send + Sync + 'static,
     F: futures::Future<Output = Result<Vec<u8>, E>>,
 {
+    let blob_name = &versioned_blob_name(blob_name, version);
     match action {
         CacheAction::FetchWrite => miss(blob_name, fetch, provider, action).await,
         _ => {
@@ -128,20 +289,353 @@ where
 /// * read from remote
 /// * if not found and can't write to remote => read disk and write to disk
 /// * if not found and can write to remote => fetch and write
+///
+/// `blob_name` is namespaced under `version`, see [`cached`].
 pub(crate) async fn cached_call<F: futures::Future<Output = Result<Vec<u8>, std::io::Error>>>(
     blob_name: &str,
     fetch: F,
     client: Option<&dyn BlobStorageProvider>,
     action: crate::fs::CacheAction,
+    version: u32,
 ) -> Result<Vec<u8>, std::io::Error> {
     let client = client.unwrap_or(&crate::fs::LocalDisk);
+    let versioned_name = versioned_blob_name(blob_name, version);
 
-    let Some(data) = client.maybe_get(blob_name).await? else {
+    let Some(data) = client.maybe_get(&versioned_name).await? else {
         if !client.can_put() {
-            return crate::fs::cached(&blob_name, fetch, &crate::fs::LocalDisk, action).await;
+            return crate::fs::cached(blob_name, fetch, &crate::fs::LocalDisk, action, version)
+                .await;
         } else {
-            return crate::fs::cached(&blob_name, fetch, client, action).await;
+            return crate::fs::cached(blob_name, fetch, client, action, version).await;
         };
     };
     Ok(data)
 }
+
+/// Deletes the blob previously cached under `blob_name` at `old_version`, once callers are
+/// confident every reader has moved on to the new `version` passed to [`cached`]/[`cached_call`].
+/// Best-effort: a provider that errors on delete just leaves the stale blob in place.
+pub async fn gc_stale_version(
+    blob_name: &str,
+    old_version: u32,
+    provider: &dyn BlobStorageProvider,
+) {
+    let stale = versioned_blob_name(blob_name, old_version);
+    if let Err(err) = provider.delete(&stale).await {
+        log::warn!("{stale} - failed to garbage collect superseded cache entry: {err}");
+    }
+}
+
+/// Extracts a blob's embedded `date=`/`month=` hive-partition key, the same convention
+/// [`CacheAction::from_date`] uses to judge freshness, so [`gc`] can decide which blobs are old
+/// enough to evict without any extra metadata beyond the blob name itself.
+fn embedded_date(blob_name: &str) -> Option<time::Date> {
+    blob_name.split('/').find_map(|segment| {
+        let (key, value) = segment.split_once('=')?;
+        match key {
+            "date" => {
+                time::Date::parse(value, time::macros::format_description!("[year]-[month]-[day]"))
+                    .ok()
+            }
+            "month" => Some(crate::serde::parse_month(value)),
+            _ => None,
+        }
+    })
+}
+
+/// A retention policy for [`gc`]: how much cached data under a prefix is worth keeping around.
+#[derive(Debug, Clone, Copy)]
+pub enum RetentionPolicy {
+    /// Evict every blob whose [`embedded_date`] is more than `max_age_days` days before today.
+    /// Blobs with no embedded date are never evicted under this policy.
+    MaxAge { max_age_days: u32 },
+    /// Keep at most `max_total_bytes` across the whole prefix, evicting the blobs with the
+    /// oldest [`embedded_date`] first until under budget. Blobs with no embedded date are
+    /// treated as the oldest, and so are evicted first.
+    MaxTotalSize { max_total_bytes: u64 },
+}
+
+/// Deletes the blobs under `prefix` that `policy` considers stale, so long-running deployments
+/// don't accumulate unbounded trace/aircraft blobs on disk (or in remote storage) without anyone
+/// manually clearing `database/`. Returns the blob names that were deleted.
+pub async fn gc(
+    provider: &dyn BlobStorageProvider,
+    prefix: &str,
+    policy: RetentionPolicy,
+) -> Result<Vec<String>, std::io::Error> {
+    let blobs = provider.list(prefix).await?;
+
+    let to_delete = match policy {
+        RetentionPolicy::MaxAge { max_age_days } => {
+            let cutoff =
+                time::OffsetDateTime::now_utc().date() - time::Duration::days(max_age_days as i64);
+            blobs
+                .into_iter()
+                .filter(|blob| embedded_date(blob).map_or(false, |date| date < cutoff))
+                .collect::<Vec<_>>()
+        }
+        RetentionPolicy::MaxTotalSize { max_total_bytes } => {
+            let mut sized = Vec::with_capacity(blobs.len());
+            for blob in blobs {
+                if let Some(data) = provider.maybe_get(&blob).await? {
+                    sized.push((blob, data.len() as u64));
+                }
+            }
+            // oldest first, so it's the first candidate evicted once over budget
+            sized.sort_by_key(|(blob, _)| embedded_date(blob).unwrap_or(time::Date::MIN));
+
+            let mut total: u64 = sized.iter().map(|(_, size)| size).sum();
+            sized
+                .into_iter()
+                .take_while(|(_, size)| {
+                    let over_budget = total > max_total_bytes;
+                    total = total.saturating_sub(*size);
+                    over_budget
+                })
+                .map(|(blob, _)| blob)
+                .collect()
+        }
+    };
+
+    for blob in &to_delete {
+        provider.delete(blob).await?;
+        log::info!("{blob} - garbage collected");
+    }
+    Ok(to_delete)
+}
+
+fn hash_of(source: &[u8]) -> u64 {
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    source.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Reads `<base_path>-v{version}.bin` and returns its contents if it was written by this
+/// exact `version` from `source` with this exact byte-for-byte content, `None` otherwise.
+fn read_versioned<T: for<'de> serde::Deserialize<'de>>(
+    base_path: &str,
+    version: u8,
+    source: &[u8],
+) -> Option<T> {
+    let bytes = std::fs::read(format!("{base_path}-v{version}.bin")).ok()?;
+    if bytes.len() < 9 {
+        return None;
+    }
+    let (header, body) = bytes.split_at(9);
+    (header[0] == version && u64::from_le_bytes(header[1..9].try_into().unwrap()) == hash_of(source))
+        .then(|| bincode::deserialize(body).ok())
+        .flatten()
+}
+
+/// Parses `source` with `parse`, caching the result to disk as a `bincode`-serialized,
+/// version-tagged blob at `<base_path>-v{version}.bin` so that subsequent calls with the
+/// same `version` and `source` skip re-parsing. The cache is invalidated whenever `version`
+/// or `source` changes, or when `refresh` is `true`.
+/// # Error
+/// Errors if `parse` fails, or if the cache file cannot be written
+pub(crate) fn cached_parse<T, E>(
+    base_path: &str,
+    version: u8,
+    source: &[u8],
+    refresh: bool,
+    parse: impl FnOnce(&[u8]) -> Result<T, E>,
+) -> Result<T, Box<dyn std::error::Error>>
+where
+    T: serde::Serialize + for<'de> serde::Deserialize<'de>,
+    E: std::error::Error + 'static,
+{
+    let cache_path = format!("{base_path}-v{version}.bin");
+
+    if !refresh {
+        if let Some(value) = read_versioned(base_path, version, source) {
+            log::info!("{cache_path} - cache hit");
+            return Ok(value);
+        }
+    }
+
+    log::info!("{cache_path} - cache miss");
+    let value = parse(source)?;
+
+    let mut encoded = vec![version];
+    encoded.extend_from_slice(&hash_of(source).to_le_bytes());
+    encoded.extend(bincode::serialize(&value)?);
+
+    if let Some(dir) = Path::new(&cache_path).parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    std::fs::write(&cache_path, encoded)?;
+    log::info!("{cache_path} - cache write");
+
+    Ok(value)
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+    use std::sync::Mutex;
+
+    use super::*;
+
+    #[test]
+    fn versioned_blob_name_namespaces_under_version() {
+        assert_eq!(versioned_blob_name("aircrafts.bin", 3), "v3/aircrafts.bin");
+    }
+
+    #[test]
+    fn sync_cursor_diff_reports_added_and_removed_relative_to_the_previous_cursor() {
+        let first = SyncCursor::default();
+        let (changes, cursor) = first.diff([("a".to_string(), 1), ("b".to_string(), 2)]);
+        assert_eq!(
+            changes,
+            vec![Change::Added("a".to_string()), Change::Added("b".to_string())]
+        );
+
+        // "a" unchanged, "b" gone, "c" new
+        let (changes, _) = cursor.diff([("a".to_string(), 1), ("c".to_string(), 3)]);
+        assert_eq!(
+            changes,
+            vec![Change::Added("c".to_string()), Change::Removed("b".to_string())]
+        );
+    }
+
+    struct InMemory(Mutex<HashMap<String, Vec<u8>>>);
+
+    #[async_trait]
+    impl BlobStorageProvider for InMemory {
+        async fn maybe_get(&self, blob_name: &str) -> Result<Option<Vec<u8>>, std::io::Error> {
+            Ok(self.0.lock().unwrap().get(blob_name).cloned())
+        }
+
+        async fn put(&self, blob_name: &str, contents: Vec<u8>) -> Result<(), std::io::Error> {
+            self.0.lock().unwrap().insert(blob_name.to_string(), contents);
+            Ok(())
+        }
+
+        fn list_stream<'a>(
+            &'a self,
+            prefix: &'a str,
+        ) -> BoxStream<'a, Result<String, std::io::Error>> {
+            let keys = self
+                .0
+                .lock()
+                .unwrap()
+                .keys()
+                .filter(|blob_name| blob_name.starts_with(prefix))
+                .cloned()
+                .map(Ok)
+                .collect::<Vec<_>>();
+            futures::stream::iter(keys).boxed()
+        }
+
+        async fn delete(&self, blob_name: &str) -> Result<(), std::io::Error> {
+            self.0.lock().unwrap().remove(blob_name);
+            Ok(())
+        }
+
+        fn can_put(&self) -> bool {
+            true
+        }
+    }
+
+    #[tokio::test]
+    async fn cached_call_treats_a_version_bump_as_a_miss() {
+        let provider = InMemory(Mutex::new(HashMap::new()));
+
+        let v0 = cached_call(
+            "data.json",
+            async { Ok::<_, std::io::Error>(b"old".to_vec()) },
+            Some(&provider as &dyn BlobStorageProvider),
+            CacheAction::ReadFetchWrite,
+            0,
+        )
+        .await
+        .unwrap();
+        assert_eq!(v0, b"old");
+
+        // bumping the version hides the v0 blob, so this re-fetches instead of returning "old"
+        let v1 = cached_call(
+            "data.json",
+            async { Ok::<_, std::io::Error>(b"new".to_vec()) },
+            Some(&provider as &dyn BlobStorageProvider),
+            CacheAction::ReadFetchWrite,
+            1,
+        )
+        .await
+        .unwrap();
+        assert_eq!(v1, b"new");
+
+        gc_stale_version("data.json", 0, &provider).await;
+        assert!(provider
+            .maybe_get("v0/data.json")
+            .await
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn embedded_date_reads_a_date_or_month_hive_key() {
+        assert_eq!(
+            embedded_date("aircraft/date=2024-01-02/data-v1.parquet"),
+            Some(time::Date::from_calendar_date(2024, time::Month::January, 2).unwrap())
+        );
+        assert_eq!(
+            embedded_date("position/icao_number=45860d/month=2024-03/data.json"),
+            Some(time::Date::from_calendar_date(2024, time::Month::March, 1).unwrap())
+        );
+        assert_eq!(embedded_date("model/db/data.csv"), None);
+    }
+
+    #[tokio::test]
+    async fn gc_max_age_deletes_only_blobs_older_than_the_cutoff() {
+        let provider = InMemory(Mutex::new(HashMap::new()));
+        let old = format!(
+            "position/icao_number=45860d/month={}/data.json",
+            crate::serde::month_to_part(
+                time::OffsetDateTime::now_utc().date() - time::Duration::days(400)
+            )
+        );
+        let recent = format!(
+            "position/icao_number=45860d/month={}/data.json",
+            crate::serde::month_to_part(time::OffsetDateTime::now_utc().date())
+        );
+        provider.put(&old, b"old".to_vec()).await.unwrap();
+        provider.put(&recent, b"recent".to_vec()).await.unwrap();
+
+        let deleted = gc(
+            &provider,
+            "position/",
+            RetentionPolicy::MaxAge { max_age_days: 365 },
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(deleted, vec![old.clone()]);
+        assert!(provider.maybe_get(&old).await.unwrap().is_none());
+        assert!(provider.maybe_get(&recent).await.unwrap().is_some());
+    }
+
+    #[tokio::test]
+    async fn gc_max_total_size_evicts_the_oldest_blobs_first_until_under_budget() {
+        let provider = InMemory(Mutex::new(HashMap::new()));
+        provider
+            .put("position/date=2024-01-01/data.json", vec![0; 5])
+            .await
+            .unwrap();
+        provider
+            .put("position/date=2024-06-01/data.json", vec![0; 5])
+            .await
+            .unwrap();
+
+        let deleted = gc(
+            &provider,
+            "position/",
+            RetentionPolicy::MaxTotalSize {
+                max_total_bytes: 5,
+            },
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(deleted, vec!["position/date=2024-01-01/data.json".to_string()]);
+    }
+}
@@ -0,0 +1,312 @@
+//! A Postgres-backed [`BlobStorageProvider`], so the leg and aircraft datasets can live in tables
+//! with real indexes and be queried concurrently, instead of only as flat CSV blobs in object
+//! storage. `put`/`maybe_get`/`list`/`delete` keep working against a generic `blobs` table for
+//! compatibility with existing callers; [`PgStore::upsert_leg`] and [`PgStore::upsert_aircraft`]
+//! are the typed path the ETL binaries should prefer, writing directly into the `legs` and
+//! `aircraft` tables instead of serializing a CSV blob per partition. [`PgStore::query_legs`]
+//! answers ad-hoc filtered questions (country, month range, emissions threshold) in SQL, instead
+//! of a caller reading and flattening every matching partition itself.
+use std::io::Error;
+use std::sync::Arc;
+
+use deadpool_postgres::{Config, Pool, Runtime};
+use futures::stream::BoxStream;
+use futures::StreamExt;
+use tokio_postgres::NoTls;
+
+use crate::aircraft::Aircraft;
+use crate::fs::BlobStorageProvider;
+
+/// Schema migrations, applied in order on [`PgStore::connect`]. Kept as plain embedded SQL (no
+/// migration framework) since the schema is small, append-only and `create ... if not exists`.
+static MIGRATIONS: &[&str] = &[
+    include_str!("fs_pg/0001_blobs.sql"),
+    include_str!("fs_pg/0002_legs.sql"),
+    include_str!("fs_pg/0003_aircraft.sql"),
+    include_str!("fs_pg/0004_legs_query_idx.sql"),
+];
+
+/// A row of the `legs` table; mirrors the flattened leg shape the ETL binaries build from
+/// [`crate::legs::Leg`] (e.g. `etl_legs`'s `LegOut`), typed for Postgres columns instead of a CSV
+/// blob.
+pub struct LegRow {
+    pub icao_number: Arc<str>,
+    pub tail_number: Arc<str>,
+    pub aircraft_model: Arc<str>,
+    pub month: time::Date,
+    pub start: time::OffsetDateTime,
+    pub start_lat: f64,
+    pub start_lon: f64,
+    pub start_altitude: f64,
+    pub end: time::OffsetDateTime,
+    pub end_lat: f64,
+    pub end_lon: f64,
+    pub end_altitude: f64,
+    pub duration: f64,
+    pub distance: f64,
+    pub great_circle_distance: f64,
+    pub hours_above_30000: f64,
+    pub hours_above_40000: f64,
+    pub co2_emissions: f64,
+}
+
+/// A pooled connection to a Postgres database backing [`BlobStorageProvider`], plus typed upserts
+/// for the `legs` and `aircraft` tables.
+pub struct PgStore {
+    pool: Pool,
+    read_only: bool,
+}
+
+impl PgStore {
+    /// Connects to `url` (a standard `postgres://` connection string), runs the embedded schema
+    /// migrations, and returns a ready-to-use pooled [`PgStore`] that accepts writes.
+    pub async fn connect(url: &str) -> Result<Self, Error> {
+        let pool = Self::pool(url)?;
+
+        let client = pool.get().await.map_err(Error::other)?;
+        for migration in MIGRATIONS {
+            client.batch_execute(migration).await.map_err(Error::other)?;
+        }
+
+        Ok(Self {
+            pool,
+            read_only: false,
+        })
+    }
+
+    /// Connects to `url` without running migrations, for a role that only has `SELECT` grants
+    /// (e.g. a reporting replica). [`BlobStorageProvider::can_put`] returns `false`, so
+    /// `crate::fs::cached_call` treats it the same way it treats a read-only [`crate::fs_s3`]
+    /// client: reads are served from it, but writes fall back to [`crate::fs::LocalDisk`].
+    pub async fn connect_read_only(url: &str) -> Result<Self, Error> {
+        Ok(Self {
+            pool: Self::pool(url)?,
+            read_only: true,
+        })
+    }
+
+    fn pool(url: &str) -> Result<Pool, Error> {
+        let mut config = Config::new();
+        config.url = Some(url.to_string());
+        config
+            .create_pool(Some(Runtime::Tokio1), NoTls)
+            .map_err(Error::other)
+    }
+
+    /// Inserts `row`, or replaces it if `(icao_number, month, start)` already exists.
+    pub async fn upsert_leg(&self, row: &LegRow) -> Result<(), Error> {
+        let client = self.pool.get().await.map_err(Error::other)?;
+        client
+            .execute(
+                "INSERT INTO legs (
+                    icao_number, tail_number, aircraft_model, month,
+                    start_time, start_lat, start_lon, start_altitude,
+                    end_time, end_lat, end_lon, end_altitude,
+                    duration, distance, great_circle_distance,
+                    hours_above_30000, hours_above_40000, co2_emissions
+                 ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17, $18)
+                 ON CONFLICT (icao_number, month, start_time) DO UPDATE SET
+                    tail_number = excluded.tail_number,
+                    aircraft_model = excluded.aircraft_model,
+                    end_time = excluded.end_time,
+                    end_lat = excluded.end_lat,
+                    end_lon = excluded.end_lon,
+                    end_altitude = excluded.end_altitude,
+                    duration = excluded.duration,
+                    distance = excluded.distance,
+                    great_circle_distance = excluded.great_circle_distance,
+                    hours_above_30000 = excluded.hours_above_30000,
+                    hours_above_40000 = excluded.hours_above_40000,
+                    co2_emissions = excluded.co2_emissions",
+                &[
+                    &row.icao_number.as_ref(),
+                    &row.tail_number.as_ref(),
+                    &row.aircraft_model.as_ref(),
+                    &row.month,
+                    &row.start,
+                    &row.start_lat,
+                    &row.start_lon,
+                    &row.start_altitude,
+                    &row.end,
+                    &row.end_lat,
+                    &row.end_lon,
+                    &row.end_altitude,
+                    &row.duration,
+                    &row.distance,
+                    &row.great_circle_distance,
+                    &row.hours_above_30000,
+                    &row.hours_above_40000,
+                    &row.co2_emissions,
+                ],
+            )
+            .await
+            .map_err(Error::other)?;
+        Ok(())
+    }
+
+    /// Inserts `aircraft`, or replaces it if `tail_number` already exists.
+    pub async fn upsert_aircraft(&self, aircraft: &Aircraft) -> Result<(), Error> {
+        let client = self.pool.get().await.map_err(Error::other)?;
+        client
+            .execute(
+                "INSERT INTO aircraft (tail_number, icao_number, type_designator, model, country)
+                 VALUES ($1, $2, $3, $4, $5)
+                 ON CONFLICT (tail_number) DO UPDATE SET
+                    icao_number = excluded.icao_number,
+                    type_designator = excluded.type_designator,
+                    model = excluded.model,
+                    country = excluded.country",
+                &[
+                    &aircraft.tail_number,
+                    &aircraft.icao_number.as_ref(),
+                    &aircraft.type_designator,
+                    &aircraft.model,
+                    &aircraft.country.as_deref(),
+                ],
+            )
+            .await
+            .map_err(Error::other)?;
+        Ok(())
+    }
+
+    /// Queries the `legs` table (joined to `aircraft` for `country`) with `filter`, pushing every
+    /// predicate down to SQL instead of reading every matching partition's blob and flattening
+    /// them in memory (as [`crate::fs::BlobStorageProvider::list`] + `maybe_get` would require).
+    pub async fn query_legs(&self, filter: &LegQuery<'_>) -> Result<Vec<LegRow>, Error> {
+        let client = self.pool.get().await.map_err(Error::other)?;
+
+        let mut clauses = Vec::new();
+        let mut params: Vec<&(dyn tokio_postgres::types::ToSql + Sync)> = Vec::new();
+        if let Some(country) = &filter.country {
+            params.push(country);
+            clauses.push(format!("a.country = ${}", params.len()));
+        }
+        if let Some(from_month) = &filter.from_month {
+            params.push(from_month);
+            clauses.push(format!("l.month >= ${}", params.len()));
+        }
+        if let Some(to_month) = &filter.to_month {
+            params.push(to_month);
+            clauses.push(format!("l.month < ${}", params.len()));
+        }
+        if let Some(min_emissions_kg) = &filter.min_emissions_kg {
+            params.push(min_emissions_kg);
+            clauses.push(format!("l.co2_emissions >= ${}", params.len()));
+        }
+
+        let where_clause = if clauses.is_empty() {
+            String::new()
+        } else {
+            format!("WHERE {}", clauses.join(" AND "))
+        };
+
+        let query = format!(
+            "SELECT l.icao_number, l.tail_number, l.aircraft_model, l.month,
+                    l.start_time, l.start_lat, l.start_lon, l.start_altitude,
+                    l.end_time, l.end_lat, l.end_lon, l.end_altitude,
+                    l.duration, l.distance, l.great_circle_distance,
+                    l.hours_above_30000, l.hours_above_40000, l.co2_emissions
+             FROM legs l JOIN aircraft a ON a.icao_number = l.icao_number
+             {where_clause}"
+        );
+
+        let rows = client.query(&query, &params).await.map_err(Error::other)?;
+        Ok(rows.iter().map(row_to_leg).collect())
+    }
+}
+
+/// Filters for [`PgStore::query_legs`]. Every field is optional; an unset one doesn't constrain
+/// the query.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct LegQuery<'a> {
+    pub country: Option<&'a str>,
+    /// Inclusive
+    pub from_month: Option<time::Date>,
+    /// Exclusive
+    pub to_month: Option<time::Date>,
+    pub min_emissions_kg: Option<f64>,
+}
+
+fn row_to_leg(row: &tokio_postgres::Row) -> LegRow {
+    LegRow {
+        icao_number: row.get::<_, String>("icao_number").into(),
+        tail_number: row.get::<_, String>("tail_number").into(),
+        aircraft_model: row.get::<_, String>("aircraft_model").into(),
+        month: row.get("month"),
+        start: row.get("start_time"),
+        start_lat: row.get("start_lat"),
+        start_lon: row.get("start_lon"),
+        start_altitude: row.get("start_altitude"),
+        end: row.get("end_time"),
+        end_lat: row.get("end_lat"),
+        end_lon: row.get("end_lon"),
+        end_altitude: row.get("end_altitude"),
+        duration: row.get("duration"),
+        distance: row.get("distance"),
+        great_circle_distance: row.get("great_circle_distance"),
+        hours_above_30000: row.get("hours_above_30000"),
+        hours_above_40000: row.get("hours_above_40000"),
+        co2_emissions: row.get("co2_emissions"),
+    }
+}
+
+#[async_trait::async_trait]
+impl BlobStorageProvider for PgStore {
+    #[must_use]
+    async fn maybe_get(&self, blob_name: &str) -> Result<Option<Vec<u8>>, Error> {
+        let client = self.pool.get().await.map_err(Error::other)?;
+        let row = client
+            .query_opt("SELECT contents FROM blobs WHERE key = $1", &[&blob_name])
+            .await
+            .map_err(Error::other)?;
+        Ok(row.map(|row| row.get::<_, Vec<u8>>("contents")))
+    }
+
+    #[must_use]
+    async fn put(&self, blob_name: &str, contents: Vec<u8>) -> Result<(), Error> {
+        let client = self.pool.get().await.map_err(Error::other)?;
+        client
+            .execute(
+                "INSERT INTO blobs (key, contents) VALUES ($1, $2)
+                 ON CONFLICT (key) DO UPDATE SET contents = excluded.contents",
+                &[&blob_name, &contents],
+            )
+            .await
+            .map_err(Error::other)?;
+        Ok(())
+    }
+
+    #[must_use]
+    async fn delete(&self, blob_name: &str) -> Result<(), Error> {
+        let client = self.pool.get().await.map_err(Error::other)?;
+        client
+            .execute("DELETE FROM blobs WHERE key = $1", &[&blob_name])
+            .await
+            .map_err(Error::other)?;
+        Ok(())
+    }
+
+    fn list_stream<'a>(&'a self, prefix: &'a str) -> BoxStream<'a, Result<String, Error>> {
+        let pattern = format!("{prefix}%");
+        futures::stream::once(async move {
+            let client = self.pool.get().await.map_err(Error::other)?;
+            let rows = client
+                .query("SELECT key FROM blobs WHERE key LIKE $1", &[&pattern])
+                .await
+                .map_err(Error::other)?;
+            Ok::<Vec<String>, Error>(rows.into_iter().map(|row| row.get("key")).collect())
+        })
+        .map(|result| {
+            futures::stream::iter(match result {
+                Ok(keys) => keys.into_iter().map(Ok).collect::<Vec<_>>(),
+                Err(err) => vec![Err(err)],
+            })
+        })
+        .flatten()
+        .boxed()
+    }
+
+    fn can_put(&self) -> bool {
+        !self.read_only
+    }
+}
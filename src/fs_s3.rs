@@ -2,25 +2,129 @@ use std::io::Error;
 
 use aws_credential_types::provider::ProvideCredentials;
 use aws_sdk_s3::{
-    config::Credentials, error::SdkError, operation::get_object::GetObjectError,
-    primitives::ByteStream, types::ObjectCannedAcl,
+    config::Credentials,
+    error::SdkError,
+    operation::get_object::GetObjectError,
+    presigning::PresigningConfig,
+    primitives::ByteStream,
+    types::{CompletedMultipartUpload, CompletedPart, ObjectCannedAcl},
 };
+use aws_smithy_runtime_api::http::Response as HttpResponse;
+use futures::{StreamExt, TryStreamExt};
+use rand::Rng;
 
 use crate::fs::BlobStorageProvider;
+
+/// Retry budget for [`with_retry`]: up to this many attempts beyond the first, exponential backoff
+/// starting at [`RETRY_BASE_BACKOFF`] and capped at [`RETRY_MAX_BACKOFF`], plus full jitter.
+const RETRY_MAX_ATTEMPTS: u32 = 5;
+const RETRY_BASE_BACKOFF: std::time::Duration = std::time::Duration::from_millis(100);
+const RETRY_MAX_BACKOFF: std::time::Duration = std::time::Duration::from_secs(10);
+
+/// Whether `err` is worth retrying: a timeout, a dropped connection, a response that couldn't be
+/// parsed, or a server response in the throttling/transient-failure range (429, 500, 503).
+/// Everything else (e.g. `NoSuchKey`, `AccessDenied`) is permanent and fails fast.
+fn is_retryable<E>(err: &SdkError<E, HttpResponse>) -> bool {
+    match err {
+        SdkError::TimeoutError(_) | SdkError::DispatchFailure(_) | SdkError::ResponseError(_) => {
+            true
+        }
+        SdkError::ServiceError(service_err) => {
+            matches!(service_err.raw().status().as_u16(), 429 | 500 | 503)
+        }
+        _ => false,
+    }
+}
+
+/// Runs `op` up to `RETRY_MAX_ATTEMPTS` additional times while [`is_retryable`] holds, sleeping
+/// between attempts for a random duration up to `min(RETRY_MAX_BACKOFF, RETRY_BASE_BACKOFF * 2^n)`
+/// (full jitter), so a single throttled request or dropped connection doesn't abort a whole run of
+/// thousands of tasks. Returns the first non-retryable (or final) error untouched.
+async fn with_retry<T, E, F, Fut>(mut op: F) -> Result<T, SdkError<E, HttpResponse>>
+where
+    F: FnMut() -> Fut,
+    Fut: std::future::Future<Output = Result<T, SdkError<E, HttpResponse>>>,
+{
+    let mut attempt = 0;
+    loop {
+        match op().await {
+            Ok(value) => return Ok(value),
+            Err(err) if attempt < RETRY_MAX_ATTEMPTS && is_retryable(&err) => {
+                let backoff = RETRY_BASE_BACKOFF
+                    .saturating_mul(1 << attempt)
+                    .min(RETRY_MAX_BACKOFF);
+                let jitter = rand::thread_rng().gen_range(std::time::Duration::ZERO..=backoff);
+                log::debug!(
+                    "retrying transient S3 error (attempt {}/{RETRY_MAX_ATTEMPTS}): {err}",
+                    attempt + 1
+                );
+                tokio::time::sleep(jitter).await;
+                attempt += 1;
+            }
+            Err(err) => return Err(err),
+        }
+    }
+}
+
 pub struct ContainerClient {
     pub client: aws_sdk_s3::Client,
     pub bucket: String,
     can_put: bool,
 }
 
+impl ContainerClient {
+    /// A time-limited URL granting anonymous GET access to `blob_name` for `expires_in`, signed
+    /// per the S3 v4 presigning scheme. Lets a caller (the website, a downstream consumer) fetch a
+    /// specific blob directly, including one that isn't `PublicRead`, without this crate handing
+    /// out the account's credentials.
+    pub async fn presigned_get(
+        &self,
+        blob_name: &str,
+        expires_in: std::time::Duration,
+    ) -> Result<String, Error> {
+        let presigning_config = PresigningConfig::expires_in(expires_in).map_err(Error::other)?;
+        let request = self
+            .client
+            .get_object()
+            .bucket(&self.bucket)
+            .key(blob_name)
+            .presigned(presigning_config)
+            .await
+            .map_err(Error::other)?;
+        Ok(request.uri().to_string())
+    }
+
+    /// A time-limited URL granting anonymous PUT access to `blob_name` for `expires_in`, signed
+    /// per the S3 v4 presigning scheme. Lets a caller upload a specific blob directly without the
+    /// account's credentials.
+    pub async fn presigned_put(
+        &self,
+        blob_name: &str,
+        expires_in: std::time::Duration,
+    ) -> Result<String, Error> {
+        let presigning_config = PresigningConfig::expires_in(expires_in).map_err(Error::other)?;
+        let request = self
+            .client
+            .put_object()
+            .bucket(&self.bucket)
+            .key(blob_name)
+            .presigned(presigning_config)
+            .await
+            .map_err(Error::other)?;
+        Ok(request.uri().to_string())
+    }
+}
+
 async fn get(client: &ContainerClient, blob_name: &str) -> Result<Option<Vec<u8>>, Error> {
-    let maybe_object = client
-        .client
-        .get_object()
-        .bucket(&client.bucket)
-        .key(blob_name)
-        .send()
-        .await;
+    let maybe_object = with_retry(|| {
+        client
+            .client
+            .get_object()
+            .bucket(&client.bucket)
+            .key(blob_name)
+            .send()
+    })
+    .await;
 
     let object = match maybe_object {
         Err(err) => match err {
@@ -44,37 +148,151 @@ async fn get(client: &ContainerClient, blob_name: &str) -> Result<Option<Vec<u8>
         .map_err(Error::other)
 }
 
+/// Above this size, `put` switches from a single `put_object` call to a multipart upload, since
+/// buffering a multi-megabyte month-position JSON into one request either fails or wastes memory.
+const MULTIPART_THRESHOLD: usize = 8 * 1024 * 1024;
+
+/// Size of each part in a multipart upload, except the last; S3 requires parts be at least 5 MiB.
+const MULTIPART_PART_SIZE: usize = 8 * 1024 * 1024;
+
 async fn put(client: &ContainerClient, blob_name: &str, content: Vec<u8>) -> Result<(), Error> {
-    let stream = ByteStream::from(content);
     let content_type = blob_name
         .ends_with(".json")
         .then_some("application/json")
         .unwrap_or("text/csv");
 
-    client
-        .client
-        .put_object()
-        .bucket(&client.bucket)
-        .key(blob_name)
-        .acl(ObjectCannedAcl::PublicRead)
-        .body(stream)
-        .content_type(content_type)
-        .send()
+    if content.len() > MULTIPART_THRESHOLD {
+        return put_multipart(client, blob_name, content, content_type).await;
+    }
+
+    with_retry(|| {
+        client
+            .client
+            .put_object()
+            .bucket(&client.bucket)
+            .key(blob_name)
+            .acl(ObjectCannedAcl::PublicRead)
+            .body(ByteStream::from(content.clone()))
+            .content_type(content_type)
+            .send()
+    })
+    .await
+    .map_err(Error::other)
+    .map(|_| ())
+}
+
+/// Uploads `content` as a multipart upload instead of a single `put_object` call. Splits `content`
+/// into [`MULTIPART_PART_SIZE`] parts (1-based, contiguous part numbers), uploads each, then
+/// completes the upload with the ordered list of part ETags. Aborts the upload on any error so no
+/// dangling parts accrue.
+async fn put_multipart(
+    client: &ContainerClient,
+    blob_name: &str,
+    content: Vec<u8>,
+    content_type: &str,
+) -> Result<(), Error> {
+    let upload_id = with_retry(|| {
+        client
+            .client
+            .create_multipart_upload()
+            .bucket(&client.bucket)
+            .key(blob_name)
+            .acl(ObjectCannedAcl::PublicRead)
+            .content_type(content_type)
+            .send()
+    })
+    .await
+    .map_err(Error::other)?
+    .upload_id()
+    .ok_or_else(|| Error::other("create_multipart_upload returned no upload id"))?
+    .to_string();
+
+    let result = match upload_parts(client, blob_name, &upload_id, content).await {
+        Ok(parts) => with_retry(|| {
+            client
+                .client
+                .complete_multipart_upload()
+                .bucket(&client.bucket)
+                .key(blob_name)
+                .upload_id(&upload_id)
+                .multipart_upload(
+                    CompletedMultipartUpload::builder()
+                        .set_parts(Some(parts.clone()))
+                        .build(),
+                )
+                .send()
+        })
         .await
         .map_err(Error::other)
-        .map(|_| ())
+        .map(|_| ()),
+        Err(err) => Err(err),
+    };
+
+    if result.is_err() {
+        // Best-effort: if the abort itself fails, the original upload error is still the one
+        // that matters to the caller. Covers both a failed `upload_parts` (dangling parts) and a
+        // failed `complete_multipart_upload` (parts uploaded but the upload never finalized).
+        let _ = client
+            .client
+            .abort_multipart_upload()
+            .bucket(&client.bucket)
+            .key(blob_name)
+            .upload_id(&upload_id)
+            .send()
+            .await;
+    }
+
+    result
 }
 
-async fn delete(client: &ContainerClient, blob_name: &str) -> Result<(), Error> {
-    client
-        .client
-        .delete_object()
-        .bucket(&client.bucket)
-        .key(blob_name)
-        .send()
+async fn upload_parts(
+    client: &ContainerClient,
+    blob_name: &str,
+    upload_id: &str,
+    content: Vec<u8>,
+) -> Result<Vec<CompletedPart>, Error> {
+    let mut parts = Vec::new();
+    for (index, chunk) in content.chunks(MULTIPART_PART_SIZE).enumerate() {
+        let part_number = (index + 1) as i32;
+        let response = with_retry(|| {
+            client
+                .client
+                .upload_part()
+                .bucket(&client.bucket)
+                .key(blob_name)
+                .upload_id(upload_id)
+                .part_number(part_number)
+                .body(ByteStream::from(chunk.to_vec()))
+                .send()
+        })
         .await
-        .map_err(Error::other)
-        .map(|_| ())
+        .map_err(Error::other)?;
+        let e_tag = response
+            .e_tag()
+            .ok_or_else(|| Error::other("upload_part returned no ETag"))?
+            .to_string();
+        parts.push(
+            CompletedPart::builder()
+                .part_number(part_number)
+                .e_tag(e_tag)
+                .build(),
+        );
+    }
+    Ok(parts)
+}
+
+async fn delete(client: &ContainerClient, blob_name: &str) -> Result<(), Error> {
+    with_retry(|| {
+        client
+            .client
+            .delete_object()
+            .bucket(&client.bucket)
+            .key(blob_name)
+            .send()
+    })
+    .await
+    .map_err(Error::other)
+    .map(|_| ())
 }
 
 #[derive(Debug)]
@@ -104,47 +322,154 @@ impl ProvideCredentials for Provider {
     }
 }
 
-/// Initialize a [`ContainerClient`] access key and secret access key
+/// Connection details for an S3-compatible backend, so [`client_with_config`] can target MinIO,
+/// Garage or plain AWS S3 instead of only the DigitalOcean Spaces defaults [`client`],
+/// [`client_from_env`] and [`anonymous_client`] fill in.
+#[derive(Debug, Clone)]
+pub struct ClientConfig {
+    pub endpoint_url: String,
+    pub region: String,
+    pub bucket: String,
+    /// Addresses objects as `https://endpoint/bucket/key` instead of
+    /// `https://bucket.endpoint/key`; most self-hosted S3-compatible servers (MinIO, Garage)
+    /// need this since they don't do wildcard-DNS bucket subdomains.
+    pub force_path_style: bool,
+}
+
+impl ClientConfig {
+    /// The DigitalOcean Spaces endpoint this crate's public dataset has always been hosted at.
+    fn digitalocean() -> Self {
+        Self {
+            endpoint_url: "https://fra1.digitaloceanspaces.com".to_string(),
+            region: "fra1".to_string(),
+            bucket: "private-jets".to_string(),
+            force_path_style: false,
+        }
+    }
+}
+
+fn configure_loader(
+    loader: aws_config::ConfigLoader,
+    config: &ClientConfig,
+) -> aws_config::ConfigLoader {
+    loader
+        .region(config.region.clone())
+        .endpoint_url(config.endpoint_url.clone())
+}
+
+fn into_client(
+    sdk_config: &aws_config::SdkConfig,
+    config: ClientConfig,
+    can_put: bool,
+) -> ContainerClient {
+    let mut builder = aws_sdk_s3::config::Builder::from(sdk_config);
+    if config.force_path_style {
+        builder = builder.force_path_style(true);
+    }
+
+    ContainerClient {
+        client: aws_sdk_s3::Client::from_conf(builder.build()),
+        bucket: config.bucket,
+        can_put,
+    }
+}
+
+/// Initialize a [`ContainerClient`] against `config` using `credentials`. The returned client
+/// can put, since supplying credentials implies write access was intended.
+pub async fn client_with_config(
+    config: ClientConfig,
+    credentials: impl ProvideCredentials + 'static,
+) -> ContainerClient {
+    let sdk_config = configure_loader(
+        aws_config::ConfigLoader::default()
+            .behavior_version(aws_config::BehaviorVersion::latest())
+            .credentials_provider(credentials),
+        &config,
+    )
+    .load()
+    .await;
+    into_client(&sdk_config, config, true)
+}
+
+/// Initialize a [`ContainerClient`] against DigitalOcean Spaces using an access key and secret
+/// access key.
 pub async fn client(access_key: String, secret_access_key: String) -> ContainerClient {
     let provider = Provider {
         access_key,
         secret_access_key,
     };
+    client_with_config(ClientConfig::digitalocean(), provider).await
+}
 
-    let config = aws_config::ConfigLoader::default()
-        .behavior_version(aws_config::BehaviorVersion::latest())
-        .region("fra1")
-        .endpoint_url("https://fra1.digitaloceanspaces.com")
-        .credentials_provider(provider)
-        .load()
-        .await;
-    let client = aws_sdk_s3::Client::new(&config);
+/// Tries each of the standard AWS credential sources in order, short-circuiting at the first one
+/// that resolves credentials: environment variables (`AWS_ACCESS_KEY_ID`/`AWS_SECRET_ACCESS_KEY`),
+/// then the shared profile file (`~/.aws/credentials`, selected by `AWS_PROFILE`), then instance
+/// metadata (IMDS) for credentials injected by a hosting cloud environment.
+#[derive(Debug)]
+struct CredentialsProviderChain {
+    environment: aws_config::environment::credentials::EnvironmentVariableCredentialsProvider,
+    profile: aws_config::profile::credentials::ProfileFileCredentialsProvider,
+    imds: aws_config::imds::credentials::ImdsCredentialsProvider,
+}
 
-    ContainerClient {
-        client,
-        bucket: "private-jets".to_string(),
-        can_put: true,
+impl CredentialsProviderChain {
+    fn new() -> Self {
+        Self {
+            environment:
+                aws_config::environment::credentials::EnvironmentVariableCredentialsProvider::new(
+                ),
+            profile: aws_config::profile::credentials::ProfileFileCredentialsProvider::builder()
+                .build(),
+            imds: aws_config::imds::credentials::ImdsCredentialsProvider::builder().build(),
+        }
     }
-}
 
-/// Initialize an anonymous [`ContainerClient`]
-pub async fn anonymous_client() -> ContainerClient {
-    let config = aws_config::ConfigLoader::default()
-        .behavior_version(aws_config::BehaviorVersion::latest())
-        .region("fra1")
-        .endpoint_url("https://fra1.digitaloceanspaces.com")
-        .no_credentials()
-        .load()
-        .await;
-    let client = aws_sdk_s3::Client::new(&config);
+    async fn resolve(&self) -> aws_credential_types::provider::Result {
+        match self.environment.provide_credentials().await {
+            Ok(credentials) => return Ok(credentials),
+            Err(err) => log::debug!("no credentials from environment variables: {err}"),
+        }
+        match self.profile.provide_credentials().await {
+            Ok(credentials) => return Ok(credentials),
+            Err(err) => log::debug!("no credentials from the shared profile file: {err}"),
+        }
+        self.imds.provide_credentials().await
+    }
+}
 
-    ContainerClient {
-        client,
-        bucket: "private-jets".to_string(),
-        can_put: false,
+impl ProvideCredentials for CredentialsProviderChain {
+    fn provide_credentials<'a>(
+        &'a self,
+    ) -> aws_credential_types::provider::future::ProvideCredentials<'a>
+    where
+        Self: 'a,
+    {
+        aws_credential_types::provider::future::ProvideCredentials::new(self.resolve())
     }
 }
 
+/// Initialize a [`ContainerClient`] against DigitalOcean Spaces using the standard AWS credential
+/// provider chain (environment variables, then the shared profile file, then instance metadata),
+/// instead of an explicit `access_key`/`secret_access_key` pair. Lets `etl_positions` run in CI or
+/// on a cloud instance without secrets passed on the command line.
+pub async fn client_from_env() -> ContainerClient {
+    client_with_config(ClientConfig::digitalocean(), CredentialsProviderChain::new()).await
+}
+
+/// Initialize an anonymous [`ContainerClient`] against DigitalOcean Spaces.
+pub async fn anonymous_client() -> ContainerClient {
+    let config = ClientConfig::digitalocean();
+    let sdk_config = configure_loader(
+        aws_config::ConfigLoader::default()
+            .behavior_version(aws_config::BehaviorVersion::latest())
+            .no_credentials(),
+        &config,
+    )
+    .load()
+    .await;
+    into_client(&sdk_config, config, false)
+}
+
 #[async_trait::async_trait]
 impl BlobStorageProvider for ContainerClient {
     #[must_use]
@@ -166,33 +491,64 @@ impl BlobStorageProvider for ContainerClient {
             .map_err(std::io::Error::other)
     }
 
-    #[must_use]
-    async fn list(&self, prefix: &str) -> Result<Vec<String>, std::io::Error> {
-        Ok(self
-            .client
+    fn list_stream<'a>(
+        &'a self,
+        prefix: &'a str,
+    ) -> futures::stream::BoxStream<'a, Result<String, std::io::Error>> {
+        self.client
             .list_objects_v2()
             .bucket(&self.bucket)
             .prefix(prefix)
             .into_paginator()
             .send()
-            .try_collect()
-            .await
-            .map_err(std::io::Error::other)?
-            .into_iter()
-            .map(|response| {
-                response
-                    .contents()
-                    .iter()
-                    .filter_map(|blob| blob.key().map(|x| x.to_string()))
-                    .collect::<Vec<_>>()
+            .map_ok(|page| {
+                futures::stream::iter(
+                    page.contents()
+                        .iter()
+                        .filter_map(|blob| blob.key().map(|x| x.to_string()))
+                        .collect::<Vec<_>>(),
+                )
+                .map(Ok)
             })
-            .flatten()
-            .collect())
+            .map_err(std::io::Error::other)
+            .try_flatten()
+            .boxed()
     }
 
     fn can_put(&self) -> bool {
         self.can_put
     }
+
+    async fn sync(
+        &self,
+        prefix: &str,
+        since: Option<crate::fs::SyncToken>,
+    ) -> Result<(Vec<crate::fs::Change>, crate::fs::SyncToken), std::io::Error> {
+        let previous = crate::fs::SyncCursor::decode(since)?;
+
+        let pages: Vec<_> = self
+            .client
+            .list_objects_v2()
+            .bucket(&self.bucket)
+            .prefix(prefix)
+            .into_paginator()
+            .send()
+            .try_collect()
+            .await
+            .map_err(std::io::Error::other)?;
+
+        let current = pages
+            .iter()
+            .flat_map(|page| page.contents())
+            .filter_map(|object| {
+                let key = object.key()?;
+                let modified = object.last_modified().map(|d| d.secs()).unwrap_or(0);
+                Some((key.to_string(), modified))
+            });
+
+        let (changes, cursor) = previous.diff(current);
+        Ok((changes, cursor.encode()?))
+    }
 }
 
 #[cfg(test)]
@@ -229,4 +585,10 @@ mod test {
     async fn init_client() {
         let _ = client("".to_string(), "".to_string()).await;
     }
+
+    #[tokio::test]
+    async fn init_client_from_env() {
+        let client = client_from_env().await;
+        assert!(client.can_put());
+    }
 }
@@ -0,0 +1,137 @@
+//! A minimal GTFS (General Transit Feed Specification) reader, used to estimate a defensible
+//! rail alternative for short private-jet legs instead of a flat multiplier.
+use std::collections::HashMap;
+use std::io::{Cursor, Read};
+
+/// Average rail emission factor, in kg CO2e per passenger-km.
+///
+/// Source: [ourworldindata.org/travel-carbon-footprint](https://ourworldindata.org/travel-carbon-footprint)
+/// (UK rail, national rail average) - retrieved on 2024-01-20.
+pub const RAIL_KG_CO2E_PER_PASSENGER_KM: f64 = 0.035;
+
+/// A rail stop is only considered a viable alternative to a leg endpoint within this radius.
+pub const MAX_STOP_DISTANCE_KM: f64 = 50.0;
+
+#[derive(Debug, Clone, serde::Deserialize)]
+struct Stop {
+    stop_id: String,
+    stop_lat: f64,
+    stop_lon: f64,
+}
+
+#[derive(Debug, Clone, serde::Deserialize)]
+struct StopTime {
+    trip_id: String,
+    stop_id: String,
+}
+
+#[derive(Debug, Clone, serde::Deserialize)]
+struct Trip {
+    trip_id: String,
+    route_id: String,
+}
+
+#[derive(Debug, Clone, serde::Deserialize)]
+struct Route {
+    route_id: String,
+}
+
+/// A GTFS feed indexed for the two questions this module answers: "what's the nearest stop to
+/// this point" and "is there a trip connecting these two stops".
+pub struct Gtfs {
+    stops: Vec<Stop>,
+    trips_by_stop: HashMap<String, Vec<String>>,
+    routes_by_trip: HashMap<String, String>,
+}
+
+impl Gtfs {
+    /// Parses a GTFS feed from the raw bytes of its zip archive, reading
+    /// `stops.txt`, `stop_times.txt`, `trips.txt` and `routes.txt`.
+    pub fn from_zip(data: &[u8]) -> Result<Self, Box<dyn std::error::Error>> {
+        let mut zip = zip::ZipArchive::new(Cursor::new(data))?;
+
+        let stops = read_csv::<Stop>(&mut zip, "stops.txt")?;
+        let stop_times = read_csv::<StopTime>(&mut zip, "stop_times.txt")?;
+        let trips = read_csv::<Trip>(&mut zip, "trips.txt")?;
+        let routes = read_csv::<Route>(&mut zip, "routes.txt")?;
+
+        let mut trips_by_stop = HashMap::<String, Vec<String>>::new();
+        for stop_time in stop_times {
+            trips_by_stop
+                .entry(stop_time.stop_id)
+                .or_default()
+                .push(stop_time.trip_id);
+        }
+
+        let route_ids = routes
+            .into_iter()
+            .map(|route| route.route_id)
+            .collect::<std::collections::HashSet<_>>();
+        let routes_by_trip = trips
+            .into_iter()
+            .filter(|trip| route_ids.contains(&trip.route_id))
+            .map(|trip| (trip.trip_id, trip.route_id))
+            .collect();
+
+        Ok(Self {
+            stops,
+            trips_by_stop,
+            routes_by_trip,
+        })
+    }
+
+    /// Returns the closest stop's `(stop_lat, stop_lon)` and `stop_id` to `pos`, if one is
+    /// within [`MAX_STOP_DISTANCE_KM`].
+    fn nearest_stop(&self, pos: (f64, f64)) -> Option<&Stop> {
+        self.stops
+            .iter()
+            .map(|stop| (stop, super::distance(pos, (stop.stop_lat, stop.stop_lon))))
+            .min_by(|(_, a), (_, b)| a.total_cmp(b))
+            .filter(|(_, distance)| *distance <= MAX_STOP_DISTANCE_KM)
+            .map(|(stop, _)| stop)
+    }
+
+    /// Returns whether a trip serves both `from` and `to`, i.e. a connecting rail service
+    /// plausibly exists between them.
+    fn has_connecting_trip(&self, from: &Stop, to: &Stop) -> bool {
+        let Some(from_trips) = self.trips_by_stop.get(&from.stop_id) else {
+            return false;
+        };
+        let Some(to_trips) = self.trips_by_stop.get(&to.stop_id) else {
+            return false;
+        };
+        from_trips
+            .iter()
+            .any(|trip_id| self.routes_by_trip.contains_key(trip_id) && to_trips.contains(trip_id))
+    }
+
+    /// Estimates the CO2e (in kg) a rail alternative would emit for a `from` -> `to` leg, from
+    /// the great-circle distance between the nearest rail stop to each endpoint times
+    /// [`RAIL_KG_CO2E_PER_PASSENGER_KM`]. Returns `None` when no nearby stations or connecting
+    /// service exist, so the caller can fall back to a flat estimate.
+    pub fn rail_emissions_kg(&self, from: (f64, f64), to: (f64, f64)) -> Option<f64> {
+        let from_stop = self.nearest_stop(from)?;
+        let to_stop = self.nearest_stop(to)?;
+        if !self.has_connecting_trip(from_stop, to_stop) {
+            return None;
+        }
+        let distance_km = super::distance(
+            (from_stop.stop_lat, from_stop.stop_lon),
+            (to_stop.stop_lat, to_stop.stop_lon),
+        );
+        Some(distance_km * RAIL_KG_CO2E_PER_PASSENGER_KM)
+    }
+}
+
+fn read_csv<D: for<'de> serde::Deserialize<'de>>(
+    zip: &mut zip::ZipArchive<Cursor<&[u8]>>,
+    name: &str,
+) -> Result<Vec<D>, Box<dyn std::error::Error>> {
+    let mut file = zip.by_name(name)?;
+    let mut contents = Vec::new();
+    file.read_to_end(&mut contents)?;
+    let rdr = csv::Reader::from_reader(Cursor::new(contents));
+    Ok(rdr
+        .into_deserialize::<D>()
+        .collect::<Result<Vec<_>, _>>()?)
+}
@@ -0,0 +1,154 @@
+//! Renders an aircraft's legs as an iCalendar (RFC 5545) `VCALENDAR`, one `VEVENT` per leg, so a
+//! journalist or activist can subscribe to a jet's movements in any calendar client instead of
+//! parsing CSV.
+
+/// The bits of a leg [`to_ical`] needs for a `VEVENT`. Decoupled from [`crate::Leg`] so it can be
+/// built either from a live leg (start/end positions known) or from a previously-aggregated
+/// `LegOut` row read back from storage.
+pub struct IcalLeg {
+    pub start: time::OffsetDateTime,
+    pub end: time::OffsetDateTime,
+    pub start_lat: f64,
+    pub start_lon: f64,
+    pub end_lat: f64,
+    pub end_lon: f64,
+    /// The distance between the leg's first and last position in km (as [`crate::Leg::distance`])
+    pub distance_km: f64,
+    pub emissions_kg: f64,
+    /// What an equivalent commercial flight would have emitted, in kg of CO2e
+    pub commercial_emissions_kg: f64,
+    /// The nearest airport's label to `start_lat`/`start_lon` (e.g. its ICAO code), or an empty
+    /// string when none is close enough
+    pub from_airport: String,
+    /// The nearest airport's label to `end_lat`/`end_lon`
+    pub to_airport: String,
+}
+
+const DATETIME_FORMAT: &[time::format_description::FormatItem<'static>] =
+    time::macros::format_description!("[year][month][day]T[hour][minute][second]Z");
+
+/// Escapes text per RFC 5545 section 3.3.11: backslash, comma, semicolon and newline are
+/// backslash-escaped.
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace(',', "\\,")
+        .replace(';', "\\;")
+        .replace('\n', "\\n")
+}
+
+fn event(tail_number: &str, model: &str, leg: &IcalLeg) -> String {
+    // Keyed on tail number + start, not icao_number: an icao_number can be reassigned to a
+    // different aircraft over time, which would otherwise collide two unrelated legs onto the
+    // same UID. Re-publishing the same leg later reproduces the same UID, so calendar clients
+    // update the existing event instead of duplicating it.
+    let uid = format!(
+        "{tail_number}-{}@private-jets",
+        leg.start.format(DATETIME_FORMAT).expect("valid timestamp")
+    );
+    let route = match (leg.from_airport.as_str(), leg.to_airport.as_str()) {
+        ("", "") => format!(
+            "{:.4},{:.4} -> {:.4},{:.4}",
+            leg.start_lat, leg.start_lon, leg.end_lat, leg.end_lon
+        ),
+        (from, to) => format!("{from} -> {to}"),
+    };
+    let summary = escape(&format!("{tail_number} ({model}): {route}"));
+    let location = escape(&route);
+    let description = escape(&format!(
+        "Distance: {:.0}km, duration: {}, CO2: {:.0}kg (commercial equivalent: {:.0}kg)",
+        leg.distance_km,
+        leg.end - leg.start,
+        leg.emissions_kg,
+        leg.commercial_emissions_kg,
+    ));
+
+    format!(
+        "BEGIN:VEVENT\r\n\
+         UID:{uid}\r\n\
+         DTSTART:{dtstart}\r\n\
+         DTEND:{dtend}\r\n\
+         SUMMARY:{summary}\r\n\
+         GEO:{lat:.6};{lon:.6}\r\n\
+         LOCATION:{location}\r\n\
+         DESCRIPTION:{description}\r\n\
+         X-EMISSIONS-KG:{emissions_kg:.0}\r\n\
+         X-COMMERCIAL-EMISSIONS-KG:{commercial_emissions_kg:.0}\r\n\
+         END:VEVENT\r\n",
+        dtstart = leg.start.format(DATETIME_FORMAT).expect("valid timestamp"),
+        dtend = leg.end.format(DATETIME_FORMAT).expect("valid timestamp"),
+        lat = leg.start_lat,
+        lon = leg.start_lon,
+        emissions_kg = leg.emissions_kg,
+        commercial_emissions_kg = leg.commercial_emissions_kg,
+    )
+}
+
+/// Renders `legs` flown by `tail_number`/`icao_number` (a given `model`) as a `VCALENDAR`
+/// containing one `VEVENT` per leg.
+pub fn to_ical(icao_number: &str, tail_number: &str, model: &str, legs: &[IcalLeg]) -> String {
+    let events = legs
+        .iter()
+        .map(|leg| event(tail_number, model, leg))
+        .collect::<String>();
+
+    format!(
+        "BEGIN:VCALENDAR\r\n\
+         VERSION:2.0\r\n\
+         PRODID:-//private-jets//{icao_number}//EN\r\n\
+         CALSCALE:GREGORIAN\r\n\
+         {events}\
+         END:VCALENDAR\r\n"
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn leg() -> IcalLeg {
+        IcalLeg {
+            start: time::macros::datetime!(2023-01-01 10:00:00 UTC),
+            end: time::macros::datetime!(2023-01-01 12:00:00 UTC),
+            start_lat: 1.0,
+            start_lon: 2.0,
+            end_lat: 3.0,
+            end_lon: 4.0,
+            distance_km: 314.0,
+            emissions_kg: 123.0,
+            commercial_emissions_kg: 12.0,
+            from_airport: String::new(),
+            to_airport: String::new(),
+        }
+    }
+
+    #[test]
+    fn renders_one_event_per_leg() {
+        let out = to_ical("abc123", "OY-ABC", "Gulfstream G650", &[leg()]);
+
+        assert!(out.starts_with("BEGIN:VCALENDAR\r\n"));
+        assert!(out.ends_with("END:VCALENDAR\r\n"));
+        assert_eq!(out.matches("BEGIN:VEVENT").count(), 1);
+        assert!(out.contains("UID:OY-ABC-20230101T100000Z@private-jets"));
+        assert!(out.contains("DTSTART:20230101T100000Z"));
+        assert!(out.contains("DTEND:20230101T120000Z"));
+        assert!(out.contains("SUMMARY:OY-ABC (Gulfstream G650): 1.0000\\,2.0000 -> 3.0000\\,4.0000"));
+        assert!(out.contains("CO2: 123kg (commercial equivalent: 12kg)"));
+        assert!(out.contains("X-EMISSIONS-KG:123"));
+        assert!(out.contains("X-COMMERCIAL-EMISSIONS-KG:12"));
+    }
+
+    #[test]
+    fn uses_nearest_airports_when_known() {
+        let mut leg = leg();
+        leg.from_airport = "EKCH".to_string();
+        leg.to_airport = "EGLL".to_string();
+        let out = to_ical("abc123", "OY-ABC", "Gulfstream G650", &[leg]);
+
+        assert!(out.contains("LOCATION:EKCH -> EGLL"));
+    }
+
+    #[test]
+    fn escapes_reserved_characters() {
+        assert_eq!(escape("a,b;c\\d\ne"), "a\\,b\\;c\\\\d\\ne");
+    }
+}
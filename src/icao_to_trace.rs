@@ -1,13 +1,22 @@
+use std::sync::Arc;
+use std::sync::OnceLock;
+
+use futures::{Stream, StreamExt};
 use rand::Rng;
 use reqwest::header;
 use reqwest::{self, StatusCode};
-use reqwest_middleware::ClientBuilder;
+use reqwest_middleware::{ClientBuilder, ClientWithMiddleware};
 use reqwest_retry::{policies::ExponentialBackoff, RetryTransientMiddleware};
 use time::Date;
 use time::OffsetDateTime;
 
 use super::Position;
-use crate::{fs, BlobStorageProvider};
+use crate::{fs, BlobStorageProvider, Region};
+
+/// Bump whenever `compute_trace`'s parsing of the cached globe-history JSON blob changes, so
+/// every previously cached response is treated as a miss and re-fetched instead of being parsed
+/// with the old assumptions.
+const TRACE_CACHE_VERSION: u32 = 0;
 
 fn last_2(icao: &str) -> &str {
     let bytes = icao.as_bytes();
@@ -40,6 +49,22 @@ fn adsbx_sid() -> String {
 
 pub(crate) static DATABASE: &'static str = "globe_history";
 
+/// A single [`ClientWithMiddleware`] shared by every adsbexchange.com request (trace history and
+/// aircraft database lookups alike), initialized on first use. Reusing one client lets `reqwest`
+/// pool and keep-alive TLS connections across the hundreds of thousands of requests a full ETL
+/// run makes, and applies the same exponential-backoff retry policy to every request path instead
+/// of only the one that happened to build its own client.
+pub(crate) fn http_client() -> &'static ClientWithMiddleware {
+    static CLIENT: OnceLock<ClientWithMiddleware> = OnceLock::new();
+    CLIENT.get_or_init(|| {
+        // Retry up to 5 times with increasing intervals between attempts.
+        let retry_policy = ExponentialBackoff::builder().build_with_max_retries(5);
+        ClientBuilder::new(reqwest::Client::new())
+            .with(RetryTransientMiddleware::new_with_policy(retry_policy))
+            .build()
+    })
+}
+
 fn cache_file_path(icao: &str, date: &time::Date) -> String {
     format!("{DATABASE}/{date}/trace_full_{icao}.json")
 }
@@ -74,13 +99,7 @@ async fn globe_history(icao: &str, date: &time::Date) -> Result<Vec<u8>, std::io
     headers.insert("Sec-Fetch-Site", "same-origin".parse().unwrap());
     headers.insert("TE", "trailers".parse().unwrap());
 
-    // Retry up to 5 times with increasing intervals between attempts.
-    let retry_policy = ExponentialBackoff::builder().build_with_max_retries(5);
-    let client = ClientBuilder::new(reqwest::Client::new())
-        .with(RetryTransientMiddleware::new_with_policy(retry_policy))
-        .build();
-
-    let response = client
+    let response = http_client()
         .get(url)
         .headers(headers)
         .send()
@@ -124,7 +143,7 @@ async fn globe_history_cached(
     let action = fs::CacheAction::from_date(&date);
     let fetch = globe_history(&icao, date);
 
-    Ok(fs::cached_call(&blob_name, fetch, client, action).await?)
+    Ok(fs::cached_call(&blob_name, fetch, client, action, TRACE_CACHE_VERSION).await?)
 }
 
 fn compute_trace(data: &[u8]) -> Result<(f64, Vec<serde_json::Value>), std::io::Error> {
@@ -237,6 +256,178 @@ pub(crate) fn cached_aircraft_positions<'a>(
     })
 }
 
+/// Live-feed counterpart to [`to_url`]'s day-keyed historical trace: adsbexchange.com's own
+/// `aircraft.json`, the snapshot its web map polls for current positions, shaped like a standard
+/// dump1090/readsb feed (a `now` timestamp plus an `aircraft` array).
+const LIVE_URL: &str = "https://globe.adsbexchange.com/data/aircraft.json";
+
+/// Bump whenever [`compute_live_positions`]'s parsing of the live snapshot changes, so every
+/// previously appended rolling-daily-blob entry is treated as a miss instead of being read back
+/// under the old assumptions.
+const LIVE_CACHE_VERSION: u32 = 0;
+
+async fn live_snapshot() -> Result<Vec<u8>, std::io::Error> {
+    let mut headers = header::HeaderMap::new();
+    headers.insert(
+        "User-Agent",
+        "Mozilla/5.0 (Macintosh; Intel Mac OS X 10.15; rv:109.0) Gecko/20100101 Firefox/118.0"
+            .parse()
+            .unwrap(),
+    );
+    headers.insert(
+        "Accept",
+        "application/json, text/javascript, */*; q=0.01"
+            .parse()
+            .unwrap(),
+    );
+    headers.insert("X-Requested-With", "XMLHttpRequest".parse().unwrap());
+    headers.insert(header::COOKIE, adsbx_sid().parse().unwrap());
+
+    let response = http_client()
+        .get(LIVE_URL)
+        .headers(headers)
+        .send()
+        .await
+        .map_err(std::io::Error::other)?;
+    response
+        .bytes()
+        .await
+        .map_err(std::io::Error::other)
+        .map(|b| b.to_vec())
+}
+
+/// Parses a [`live_snapshot`] response into `(icao, position)` pairs. `seen_pos` (seconds since
+/// `now` that a position was last updated) is honored when present, so a stale entry isn't
+/// timestamped as if it had just been observed.
+fn compute_live_positions(data: &[u8]) -> Result<Vec<(Arc<str>, Position)>, std::io::Error> {
+    let value: serde_json::Value = serde_json::from_slice(data)?;
+    let now = value.get("now").and_then(|x| x.as_f64()).unwrap_or(0.0);
+    let Some(aircraft) = value.get("aircraft").and_then(|x| x.as_array()) else {
+        return Ok(vec![]);
+    };
+
+    Ok(aircraft
+        .iter()
+        .filter_map(|entry| {
+            let icao: Arc<str> = entry.get("hex")?.as_str()?.into();
+            let latitude = entry.get("lat")?.as_f64()?;
+            let longitude = entry.get("lon")?.as_f64()?;
+            let seen_pos = entry.get("seen_pos").and_then(|x| x.as_f64()).unwrap_or(0.0);
+            let datetime = OffsetDateTime::from_unix_timestamp((now - seen_pos) as i64).ok()?;
+            let altitude = match entry.get("alt_baro") {
+                Some(v) if v.as_str() == Some("ground") => None,
+                Some(v) => v.as_f64(),
+                None => None,
+            };
+            Some((
+                icao,
+                Position {
+                    datetime,
+                    latitude,
+                    longitude,
+                    altitude,
+                },
+            ))
+        })
+        .collect())
+}
+
+fn live_blob_name(day: time::Date) -> String {
+    format!("{DATABASE}/live/{day}.json")
+}
+
+/// Appends `position` to the rolling per-day snapshot blob for its date. Always re-fetches and
+/// re-writes (via [`fs::CacheAction::FetchWrite`]) rather than trusting a cached read, since the
+/// blob for "today" is still being appended to by every poll.
+async fn append_live_position(
+    icao: &Arc<str>,
+    position: &Position,
+    client: &dyn BlobStorageProvider,
+) -> Result<(), std::io::Error> {
+    let blob_name = live_blob_name(position.datetime().date());
+    let versioned_name = fs::versioned_blob_name(&blob_name, LIVE_CACHE_VERSION);
+    let icao = icao.clone();
+    let position = position.clone();
+    let fetch = async {
+        let mut positions: Vec<(Arc<str>, Position)> = match client.maybe_get(&versioned_name).await? {
+            Some(data) => serde_json::from_slice(&data)?,
+            None => Vec::new(),
+        };
+        positions.push((icao, position));
+        let mut bytes = Vec::new();
+        serde_json::to_writer(&mut bytes, &positions)?;
+        Result::<_, std::io::Error>::Ok(bytes)
+    };
+
+    fs::cached(&blob_name, fetch, client, fs::CacheAction::FetchWrite, LIVE_CACHE_VERSION).await?;
+    Ok(())
+}
+
+/// Incrementally folds per-aircraft positions into completed [`crate::Leg`]s via one
+/// [`crate::LegBuilder`] per icao, so a still-open in-flight sequence is never surfaced as a
+/// completed leg (see [`crate::LegBuilder`]'s doc for why that rules out re-running
+/// [`crate::legs`] over the growing buffer).
+#[derive(Default)]
+struct LiveLegs {
+    builders: std::collections::HashMap<Arc<str>, crate::LegBuilder>,
+}
+
+impl LiveLegs {
+    /// Feeds one more observed `position` for `icao`. Returns the newly completed leg if this
+    /// position's arrival tipped that aircraft from flying to grounded.
+    fn push(&mut self, icao: Arc<str>, position: Position) -> Option<crate::Leg> {
+        self.builders.entry(icao).or_default().push(position)
+    }
+}
+
+/// Repeatedly polls the live feed for current aircraft positions inside `region`, every
+/// `interval`, deduplicating on `(icao, datetime)` so a duplicate or late-arriving sample is
+/// dropped before it reaches the leg builder. When `client` is given, every fresh position is
+/// also appended to its day's rolling blob via [`append_live_position`].
+///
+/// The returned stream never completes on its own: it's meant to be polled for as long as the
+/// caller wants to keep tailing `region`, e.g. a `--live` binary running until killed.
+pub fn live_legs<'a>(
+    region: Region,
+    interval: std::time::Duration,
+    client: Option<&'a dyn BlobStorageProvider>,
+) -> impl Stream<Item = Result<(Arc<str>, crate::Leg), std::io::Error>> + 'a {
+    let state = (
+        LiveLegs::default(),
+        std::collections::HashSet::<(Arc<str>, OffsetDateTime)>::new(),
+    );
+
+    futures::stream::unfold(state, move |(mut builder, mut seen)| async move {
+        tokio::time::sleep(interval).await;
+
+        let positions = match live_snapshot().await.and_then(|data| compute_live_positions(&data)) {
+            Ok(positions) => positions,
+            Err(err) => return Some((vec![Err(err)], (builder, seen))),
+        };
+
+        let mut completed = Vec::new();
+        for (icao, position) in positions {
+            if !region.contains(&position) {
+                continue;
+            }
+            if !seen.insert((icao.clone(), position.datetime())) {
+                continue; // duplicate or late sample: already fed to the leg builder
+            }
+            if let Some(client) = client {
+                if let Err(err) = append_live_position(&icao, &position, client).await {
+                    log::warn!("failed to append live position for {icao}: {err}");
+                }
+            }
+            if let Some(leg) = builder.push(icao.clone(), position) {
+                completed.push(Ok((icao, leg)));
+            }
+        }
+
+        Some((completed, (builder, seen)))
+    })
+    .flat_map(futures::stream::iter)
+}
+
 pub use crate::trace_month::*;
 
 #[cfg(test)]
@@ -1,4 +1,4 @@
-use crate::Position;
+use crate::{Position, Region};
 
 /// Represents a leg, also known as a [non-stop flight](https://en.wikipedia.org/wiki/Non-stop_flight)
 /// between two positions.
@@ -20,11 +20,40 @@ impl Leg {
         self.from().distace(&self.to())
     }
 
-    /// The total two-dimensional length of the leg in km
+    /// The total two-dimensional length of the leg in km: the sum of the geodesic distance
+    /// between every pair of consecutive positions, as opposed to [`Leg::distance`]'s direct
+    /// start-to-end distance.
     pub fn length(&self) -> f64 {
         self.positions.windows(2).map(|w| w[0].distace(&w[1])).sum()
     }
 
+    /// Like [`Leg::length`], but accounts for altitude: each `positions.windows(2)` segment's
+    /// horizontal geodesic distance is combined with its altitude delta (converted from feet to
+    /// km) via `sqrt(horizontal² + vertical²)`. Segments where either end is grounded (no
+    /// altitude reading) fall back to the horizontal distance alone.
+    pub fn length_3d(&self) -> f64 {
+        const FEET_TO_KM: f64 = 0.0003048;
+
+        self.positions
+            .windows(2)
+            .map(|w| {
+                let horizontal = w[0].distace(&w[1]);
+                if w[0].grounded() || w[1].grounded() {
+                    return horizontal;
+                }
+                let vertical = (w[1].altitude() - w[0].altitude()) * FEET_TO_KM;
+                (horizontal.powi(2) + vertical.powi(2)).sqrt()
+            })
+            .sum()
+    }
+
+    /// How much the leg deviated from a direct route: [`Leg::length`] divided by
+    /// [`Leg::distance`]. `1.0` means the aircraft flew the direct great-circle route; higher
+    /// values indicate detours, holding patterns or diversions.
+    pub fn gc_detour_ratio(&self) -> f64 {
+        self.length() / self.distance()
+    }
+
     /// Leg duration
     pub fn duration(&self) -> time::Duration {
         self.to().datetime() - self.from().datetime()
@@ -117,6 +146,45 @@ impl<I: Iterator<Item = Position>> Iterator for Legs<I> {
     }
 }
 
+/// Incrementally folds individually-pushed [`Position`]s into completed [`Leg`]s, applying the
+/// same [`landed`]/noise-filtering rules as [`legs`] step by step instead of over a whole buffered
+/// sequence. Unlike [`Legs`], which also yields its still-open trailing sequence once the input
+/// iterator is exhausted (correct for finite historical data, where "exhausted" means "no more
+/// data exists"), [`LegBuilder::push`] only ever emits a leg the instant [`landed`] fires on the
+/// newly pushed position - so a caller streaming live positions for an aircraft that hasn't landed
+/// yet never mistakes "no more positions observed so far" for "landed".
+#[derive(Default)]
+pub struct LegBuilder {
+    previous_position: Option<Position>,
+    sequence: Vec<Position>,
+}
+
+impl LegBuilder {
+    /// Feeds one more observed `position`. Returns the newly completed leg if this position's
+    /// arrival tipped the aircraft from flying to grounded, after [`legs`]'s usual
+    /// duration/distance noise filters.
+    pub fn push(&mut self, position: Position) -> Option<Leg> {
+        let previous_position = self.previous_position.replace(position.clone())?;
+
+        if !is_grounded(&previous_position, &position) {
+            if self.sequence.is_empty() {
+                self.sequence.push(previous_position.clone());
+            }
+            self.sequence.push(position.clone());
+        }
+
+        if landed(&previous_position, &position) && !self.sequence.is_empty() {
+            let leg = Leg {
+                positions: std::mem::take(&mut self.sequence),
+            };
+            if leg.duration() > time::Duration::minutes(5) && leg.distance() > 3.0 {
+                return Some(leg);
+            }
+        }
+        None
+    }
+}
+
 /// Returns a set of [`Leg`]s from a sequence of [`Position`]s according
 /// to the [methodology `M-identify-legs`](../methodology.md).
 pub fn legs(positions: impl Iterator<Item = Position>) -> impl Iterator<Item = Leg> {
@@ -127,6 +195,15 @@ pub fn legs(positions: impl Iterator<Item = Position>) -> impl Iterator<Item = L
         .filter(|leg| leg.distance() > 3.0)
 }
 
+/// Filters `legs` down to those whose first and last position both fall inside `region`, e.g. to
+/// scope a report to a country or an airport's vicinity.
+pub fn legs_within<'a>(
+    legs: impl Iterator<Item = Leg> + 'a,
+    region: &'a Region,
+) -> impl Iterator<Item = Leg> + 'a {
+    legs.filter(move |leg| region.contains(leg.from()) && region.contains(leg.to()))
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -243,4 +320,132 @@ mod test {
             ],
         );
     }
+
+    fn geo_pos(t: i64, lat: f64, lon: f64, altitude: Option<f64>) -> Position {
+        Position {
+            datetime: time::OffsetDateTime::from_unix_timestamp(t).unwrap(),
+            latitude: lat,
+            longitude: lon,
+            altitude,
+        }
+    }
+
+    #[test]
+    fn length_3d_adds_altitude_delta_when_airborne() {
+        let leg = Leg {
+            // same lat/lon: horizontal distance is 0, so length_3d is exactly the altitude delta
+            positions: vec![
+                geo_pos(0, 52.0, 4.0, Some(0.0)),
+                geo_pos(1, 52.0, 4.0, Some(1000.0)),
+            ],
+        };
+        assert!((leg.length_3d() - 1000.0 * 0.0003048).abs() < 1e-9);
+    }
+
+    #[test]
+    fn length_3d_ignores_altitude_when_grounded() {
+        let leg = Leg {
+            positions: vec![geo_pos(0, 52.0, 4.0, None), geo_pos(1, 52.1, 4.0, None)],
+        };
+        assert_eq!(leg.length_3d(), leg.length());
+    }
+
+    #[test]
+    fn gc_detour_ratio_is_one_for_a_direct_leg() {
+        let leg = Leg {
+            positions: vec![
+                geo_pos(0, 52.0, 4.0, Some(30000.0)),
+                geo_pos(1, 53.0, 4.0, Some(30000.0)),
+            ],
+        };
+        assert_eq!(leg.gc_detour_ratio(), 1.0);
+    }
+
+    #[test]
+    fn legs_within_keeps_only_legs_inside_the_region() {
+        let region = Region {
+            upper_lat: 58.0,
+            lower_lat: 54.0,
+            upper_lon: 13.0,
+            lower_lon: 8.0,
+            floor: None,
+            ceiling: None,
+        };
+        let inside = Leg {
+            positions: vec![
+                geo_pos(0, 56.0, 10.0, Some(30000.0)),
+                geo_pos(1, 56.5, 10.5, Some(30000.0)),
+            ],
+        };
+        let outside = Leg {
+            positions: vec![
+                geo_pos(0, 56.0, 10.0, Some(30000.0)),
+                geo_pos(1, 40.0, 10.0, Some(30000.0)),
+            ],
+        };
+
+        let kept = legs_within(vec![inside.clone(), outside].into_iter(), &region)
+            .collect::<Vec<_>>();
+        assert_eq!(kept, vec![inside]);
+    }
+
+    #[test]
+    fn gc_detour_ratio_exceeds_one_for_a_zigzag_leg() {
+        let leg = Leg {
+            positions: vec![
+                geo_pos(0, 52.0, 4.0, Some(30000.0)),
+                geo_pos(1, 55.0, 4.0, Some(30000.0)),
+                geo_pos(2, 52.0, 4.0, Some(30000.0)),
+                geo_pos(3, 53.0, 4.0, Some(30000.0)),
+            ],
+        };
+        assert!(leg.gc_detour_ratio() > 1.0);
+    }
+
+    #[test]
+    fn leg_builder_stays_quiet_while_still_airborne() {
+        let pos = |(t, altitude): (i64, Option<f64>)| Position {
+            datetime: time::OffsetDateTime::from_unix_timestamp(t).unwrap(),
+            latitude: 0.0,
+            longitude: 0.0,
+            altitude,
+        };
+
+        let mut builder = LegBuilder::default();
+        // unlike `legs()`, which would surface this still-open sequence as a leg once its input
+        // iterator is exhausted, an aircraft that is still flying must never emit one.
+        for position in [(0, None), (1, Some(2.1)), (2, Some(2.1))] {
+            assert_eq!(builder.push(pos(position)), None);
+        }
+    }
+
+    #[test]
+    fn leg_builder_emits_exactly_once_on_landing() {
+        // duration/distance both clear legs()'s noise filters: ~52->53 lat is >3km and the leg
+        // spans 311s (>5min).
+        let takeoff = geo_pos(0, 52.0, 4.0, None);
+        let climb = geo_pos(10, 52.0, 4.0, Some(2000.0));
+        let cruise = geo_pos(301, 53.0, 4.0, Some(2000.0));
+        let landing = geo_pos(311, 53.0, 4.0, None);
+
+        let mut builder = LegBuilder::default();
+        let mut completed = vec![];
+        for position in [takeoff.clone(), climb, cruise, landing.clone()] {
+            completed.extend(builder.push(position));
+        }
+        // still grounded after landing: no second, spurious leg for the same sequence.
+        assert_eq!(builder.push(geo_pos(320, 53.0, 4.0, None)), None);
+
+        assert_eq!(
+            completed,
+            vec![Leg {
+                positions: vec![
+                    takeoff,
+                    geo_pos(10, 52.0, 4.0, Some(2000.0)),
+                    geo_pos(301, 53.0, 4.0, Some(2000.0)),
+                    landing,
+                ]
+            }]
+        );
+    }
 }
@@ -3,11 +3,17 @@ pub mod aircraft;
 mod aircraft_models;
 mod aircraft_owners;
 mod airports;
+pub mod beast;
+pub mod blob_object_store;
 mod country;
 pub mod csv;
 mod emissions;
+pub mod filter;
 pub(crate) mod fs;
+pub mod fs_pg;
 pub mod fs_s3;
+mod gtfs;
+pub mod ical;
 mod icao_to_trace;
 pub mod io;
 mod legs;
@@ -15,8 +21,11 @@ mod model;
 mod owners;
 mod private_emissions;
 mod private_jets_in_time;
+mod routes;
+pub mod sd_notify;
 pub mod serde;
 mod trace_month;
+pub mod trajectory;
 
 pub use aircraft_models::*;
 pub use aircraft_owners::*;
@@ -24,12 +33,14 @@ pub use airports::*;
 pub(crate) use country::CountryIcaoRanges;
 pub use emissions::*;
 pub use fs::{BlobStorageProvider, LocalDisk};
+pub use gtfs::*;
 pub use icao_to_trace::*;
 pub use legs::*;
 pub use model::*;
 pub use owners::*;
 pub use private_emissions::*;
-pub use private_jets_in_time::private_jets_in_month;
+pub use private_jets_in_time::{private_jets_in_month, Filter};
+pub use routes::*;
 
 /// A position of an aircraft
 #[derive(Debug, Clone, PartialEq, ::serde::Serialize, ::serde::Deserialize)]
@@ -79,13 +90,143 @@ impl Position {
     }
 }
 
-/// Returns the distance between two geo-points in km
+/// Returns the geodesic distance between two geo-points in km, computed over the WGS-84
+/// ellipsoid via Vincenty's formula. Falls back to the (less accurate, but always-converging)
+/// haversine great-circle distance for the rare pairs Vincenty fails to converge on, e.g.
+/// near-antipodal points.
 fn distance(from: (f64, f64), to: (f64, f64)) -> f64 {
+    vincenty_distance(from, to).unwrap_or_else(|| haversine_distance(from, to))
+}
+
+/// Great-circle distance between two `(latitude, longitude)` points in km, treating earth as a
+/// sphere. Always converges, but diverges from the WGS-84 ellipsoid by up to ~0.5% depending on
+/// latitude; used as [`vincenty_distance`]'s fallback.
+fn haversine_distance(from: (f64, f64), to: (f64, f64)) -> f64 {
     let from = geoutils::Location::new(from.0, from.1);
     let to = geoutils::Location::new(to.0, to.1);
     from.haversine_distance_to(&to).meters() / 1000.0
 }
 
+/// WGS-84 ellipsoid semi-major axis, in km.
+const WGS84_A: f64 = 6378.137;
+/// WGS-84 ellipsoid flattening.
+const WGS84_F: f64 = 1.0 / 298.257223563;
+
+/// Geodesic distance between two `(latitude, longitude)` points in km, via Vincenty's inverse
+/// formula over the WGS-84 ellipsoid. Returns `None` if the iteration fails to converge, which
+/// happens for some near-antipodal point pairs.
+fn vincenty_distance(from: (f64, f64), to: (f64, f64)) -> Option<f64> {
+    let b = WGS84_A * (1.0 - WGS84_F);
+    let (lat1, lon1) = (from.0.to_radians(), from.1.to_radians());
+    let (lat2, lon2) = (to.0.to_radians(), to.1.to_radians());
+
+    let u1 = ((1.0 - WGS84_F) * lat1.tan()).atan();
+    let u2 = ((1.0 - WGS84_F) * lat2.tan()).atan();
+    let l = lon2 - lon1;
+
+    let (sin_u1, cos_u1) = u1.sin_cos();
+    let (sin_u2, cos_u2) = u2.sin_cos();
+
+    let mut lambda = l;
+    let mut iter_limit = 100;
+    let (mut sin_sigma, mut cos_sigma, mut sigma, mut cos_sq_alpha, mut cos_2sigma_m);
+    loop {
+        let (sin_lambda, cos_lambda) = lambda.sin_cos();
+        sin_sigma = ((cos_u2 * sin_lambda).powi(2)
+            + (cos_u1 * sin_u2 - sin_u1 * cos_u2 * cos_lambda).powi(2))
+        .sqrt();
+        if sin_sigma == 0.0 {
+            return Some(0.0); // coincident points
+        }
+        cos_sigma = sin_u1 * sin_u2 + cos_u1 * cos_u2 * cos_lambda;
+        sigma = sin_sigma.atan2(cos_sigma);
+        let sin_alpha = cos_u1 * cos_u2 * sin_lambda / sin_sigma;
+        cos_sq_alpha = 1.0 - sin_alpha.powi(2);
+        cos_2sigma_m = if cos_sq_alpha == 0.0 {
+            0.0 // equatorial line
+        } else {
+            cos_sigma - 2.0 * sin_u1 * sin_u2 / cos_sq_alpha
+        };
+        let c = WGS84_F / 16.0 * cos_sq_alpha * (4.0 + WGS84_F * (4.0 - 3.0 * cos_sq_alpha));
+        let lambda_prev = lambda;
+        lambda = l
+            + (1.0 - c)
+                * WGS84_F
+                * sin_alpha
+                * (sigma
+                    + c * sin_sigma
+                        * (cos_2sigma_m
+                            + c * cos_sigma * (-1.0 + 2.0 * cos_2sigma_m.powi(2))));
+
+        iter_limit -= 1;
+        if (lambda - lambda_prev).abs() <= 1e-12 {
+            break;
+        }
+        if iter_limit == 0 {
+            return None;
+        }
+    }
+
+    let u_sq = cos_sq_alpha * (WGS84_A.powi(2) - b.powi(2)) / b.powi(2);
+    let big_a = 1.0 + u_sq / 16384.0 * (4096.0 + u_sq * (-768.0 + u_sq * (320.0 - 175.0 * u_sq)));
+    let big_b = u_sq / 1024.0 * (256.0 + u_sq * (-128.0 + u_sq * (74.0 - 47.0 * u_sq)));
+    let delta_sigma = big_b
+        * sin_sigma
+        * (cos_2sigma_m
+            + big_b / 4.0
+                * (cos_sigma * (-1.0 + 2.0 * cos_2sigma_m.powi(2))
+                    - big_b / 6.0
+                        * cos_2sigma_m
+                        * (-3.0 + 4.0 * sin_sigma.powi(2))
+                        * (-3.0 + 4.0 * cos_2sigma_m.powi(2))));
+
+    Some(b * big_a * (sigma - delta_sigma))
+}
+
+/// A geographic bounding box, plus an optional altitude band, used to scope analysis to a region
+/// (e.g. a country or an airport's vicinity) instead of a tail-number prefix hack like
+/// `starts_with("OY-")`. Modeled on the live-traffic area config from the live-ATC tooling.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Region {
+    pub upper_lat: f64,
+    pub lower_lat: f64,
+    pub upper_lon: f64,
+    pub lower_lon: f64,
+    /// Altitude in feet below which a position is outside the region. `None` means no floor.
+    /// Grounded positions have [`Position::altitude`] `0.0`, so `floor: Some(0.0)` (or `None`)
+    /// includes taxiing aircraft, while any positive floor excludes them.
+    pub floor: Option<f64>,
+    /// Altitude in feet above which a position is outside the region. `None` means no ceiling.
+    pub ceiling: Option<f64>,
+}
+
+impl Region {
+    /// Whether `p` falls inside this region's bounding box and altitude band. A plain comparison
+    /// against the box edges, no haversine involved. Longitude wraps around the antimeridian when
+    /// `lower_lon > upper_lon` (e.g. a box spanning 170°E to -170°E).
+    pub fn contains(&self, p: &Position) -> bool {
+        let in_latitude = (self.lower_lat..=self.upper_lat).contains(&p.latitude());
+        let in_longitude = if self.lower_lon > self.upper_lon {
+            p.longitude() >= self.lower_lon || p.longitude() <= self.upper_lon
+        } else {
+            (self.lower_lon..=self.upper_lon).contains(&p.longitude())
+        };
+        let altitude = p.altitude();
+        let above_floor = self.floor.map_or(true, |floor| altitude >= floor);
+        let below_ceiling = self.ceiling.map_or(true, |ceiling| altitude <= ceiling);
+
+        in_latitude && in_longitude && above_floor && below_ceiling
+    }
+}
+
+/// Filters `positions` down to those [`Region::contains`]ed by `region`.
+pub fn filter_positions<'a>(
+    positions: impl Iterator<Item = Position> + 'a,
+    region: &'a Region,
+) -> impl Iterator<Item = Position> + 'a {
+    positions.filter(move |p| region.contains(p))
+}
+
 /// An iterator between two [`time::Date`]s in increments
 /// The result is exclusive, i.e. the iterator has two items when increment is one day
 /// from 2022-01-01 and 2022-01-03
@@ -126,4 +267,77 @@ mod test {
             vec![date!(2022 - 01 - 01), date!(2022 - 01 - 02)]
         );
     }
+
+    fn pos(lat: f64, lon: f64, altitude: Option<f64>) -> Position {
+        Position {
+            datetime: time::OffsetDateTime::from_unix_timestamp(0).unwrap(),
+            latitude: lat,
+            longitude: lon,
+            altitude,
+        }
+    }
+
+    #[test]
+    fn region_contains_checks_the_bounding_box() {
+        let region = Region {
+            upper_lat: 58.0,
+            lower_lat: 54.0,
+            upper_lon: 13.0,
+            lower_lon: 8.0,
+            floor: None,
+            ceiling: None,
+        };
+        assert!(region.contains(&pos(56.0, 10.0, Some(30000.0))));
+        assert!(!region.contains(&pos(60.0, 10.0, Some(30000.0))));
+        assert!(!region.contains(&pos(56.0, 20.0, Some(30000.0))));
+    }
+
+    #[test]
+    fn region_contains_wraps_across_the_antimeridian() {
+        let region = Region {
+            upper_lat: 10.0,
+            lower_lat: -10.0,
+            upper_lon: -170.0,
+            lower_lon: 170.0,
+            floor: None,
+            ceiling: None,
+        };
+        assert!(region.contains(&pos(0.0, 175.0, None)));
+        assert!(region.contains(&pos(0.0, -175.0, None)));
+        assert!(!region.contains(&pos(0.0, 0.0, None)));
+    }
+
+    #[test]
+    fn region_altitude_band_treats_grounded_as_zero() {
+        let region = Region {
+            upper_lat: 10.0,
+            lower_lat: -10.0,
+            upper_lon: 10.0,
+            lower_lon: -10.0,
+            floor: Some(0.0),
+            ceiling: Some(10000.0),
+        };
+        assert!(region.contains(&pos(0.0, 0.0, None)));
+        assert!(region.contains(&pos(0.0, 0.0, Some(5000.0))));
+        assert!(!region.contains(&pos(0.0, 0.0, Some(20000.0))));
+
+        let region_with_floor = Region {
+            floor: Some(1000.0),
+            ..region
+        };
+        assert!(!region_with_floor.contains(&pos(0.0, 0.0, None)));
+    }
+
+    #[test]
+    fn distance_matches_known_geodesic() {
+        let amsterdam = (52.3667, 4.9041);
+        let paris = (48.8566, 2.3522);
+
+        assert_eq!(distance(amsterdam, amsterdam), 0.0);
+        assert!((distance(amsterdam, paris) - distance(paris, amsterdam)).abs() < 1e-6);
+
+        // Amsterdam-Paris is ~430km as the crow flies
+        let d = distance(amsterdam, paris);
+        assert!((420.0..440.0).contains(&d), "got {d}");
+    }
 }
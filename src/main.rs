@@ -119,7 +119,7 @@ pub fn main() -> Result<(), Box<dyn Error>> {
 
     std::fs::create_dir_all("database")?;
 
-    let owners = load_owners()?;
+    let owners = load_owners(false)?;
     let aircrafts = load_aircrafts()?;
 
     let dane_emissions_kg = Fact {
@@ -2,6 +2,8 @@ use std::{collections::HashMap, error::Error, sync::Arc};
 
 use serde::{Deserialize, Serialize};
 
+use crate::fs;
+
 /// A map of the aircraft model (e.g. `BEECH 400 Beechjet`) to an [`AircraftModel`].
 pub type AircraftModels = HashMap<String, Arc<AircraftModel>>;
 
@@ -20,45 +22,53 @@ pub struct AircraftModel {
 
 static MODELS: &'static [u8] = include_bytes!("./models.csv");
 
+static CACHE_PATH: &str = "database/models";
+const CACHE_VERSION: u8 = 1;
+
 /// Returns the set of all [`AircraftModel`] in `src/models.csv`,
 /// corresponding to aircraft types whose primary use is to be a private jet
 /// according to the [methodology `M-models-for-private-use`](../methodology.md).
 /// The gph of each model is the average over all sources as per [methodology `M-average-consumption`](../methodology.md).
+/// The computed map is cached as a versioned binary blob at `database/models-v1.bin` so that
+/// subsequent runs skip re-parsing and re-aggregating the embedded CSV.
+/// Pass `refresh = true` to force a rebuild.
 /// # Error
 /// Errors if the file cannot be read
-pub fn load_private_jet_models() -> Result<AircraftModels, Box<dyn Error>> {
-    let data = super::csv::deserialize(MODELS)
-        .map(|x| x.unwrap())
-        .map(|a: AircraftModel| (a.clone(), a))
-        .collect::<Vec<_>>();
+pub fn load_private_jet_models(refresh: bool) -> Result<AircraftModels, Box<dyn Error>> {
+    fs::cached_parse(CACHE_PATH, CACHE_VERSION, MODELS, refresh, |data| {
+        let data = super::csv::deserialize(data)
+            .map(|x| x.unwrap())
+            .map(|a: AircraftModel| (a.clone(), a))
+            .collect::<Vec<_>>();
 
-    let data = data
-        .into_iter()
-        .fold(
-            HashMap::<String, (AircraftModel, u32)>::default(),
-            |mut acc, (a, b)| {
-                // a == b in this case
-                acc.entry(a.model)
-                    .and_modify(|x: &mut (AircraftModel, u32)| {
-                        x.0.source.push(';');
-                        x.0.source.push_str(&a.source);
-                        x.0.date.push(';');
-                        x.0.date.push_str(&a.date);
-                        x.0.gph += a.gph;
-                        x.1 += 1;
-                    })
-                    .or_insert((b, 1));
-                acc
-            },
-        )
-        .into_iter()
-        .map(|(model, (mut all, count))| {
-            all.gph /= count;
-            (model, Arc::new(all))
-        })
-        .collect();
+        let data = data
+            .into_iter()
+            .fold(
+                HashMap::<String, (AircraftModel, u32)>::default(),
+                |mut acc, (a, b)| {
+                    // a == b in this case
+                    acc.entry(a.model)
+                        .and_modify(|x: &mut (AircraftModel, u32)| {
+                            x.0.source.push(';');
+                            x.0.source.push_str(&a.source);
+                            x.0.date.push(';');
+                            x.0.date.push_str(&a.date);
+                            x.0.gph += a.gph;
+                            x.1 += 1;
+                        })
+                        .or_insert((b, 1));
+                    acc
+                },
+            )
+            .into_iter()
+            .map(|(model, (mut all, count))| {
+                all.gph /= count;
+                (model, Arc::new(all))
+            })
+            .collect();
 
-    Ok(data)
+        Ok::<_, Box<dyn Error>>(data)
+    })
 }
 
 #[cfg(test)]
@@ -67,7 +77,7 @@ mod test {
 
     #[test]
     fn models() {
-        let models = load_private_jet_models().unwrap();
+        let models = load_private_jet_models(false).unwrap();
 
         assert_eq!(
             models.get("GULFSTREAM 5").unwrap().gph,
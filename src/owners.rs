@@ -1,16 +1,26 @@
 use std::{collections::HashMap, error::Error};
 
-use crate::{Company, Fact};
+use crate::{fs, Company, Fact};
 
 pub type Owners = HashMap<String, Company>;
 
-/// Loads owners json into memory
-pub fn load_owners() -> Result<HashMap<String, Company>, Box<dyn Error>> {
+static CACHE_PATH: &str = "database/owners";
+const CACHE_VERSION: u8 = 1;
+
+/// Loads owners json into memory, caching the parsed result as a versioned binary blob
+/// at `database/owners-v1.bin` so that subsequent runs skip re-parsing `src/owners.json`.
+/// Pass `refresh = true` to force a re-parse, e.g. after `src/owners.json` changed.
+pub fn load_owners(refresh: bool) -> Result<Owners, Box<dyn Error>> {
     let data = std::fs::read("src/owners.json")?;
-    let value: HashMap<String, Fact<String>> = serde_json::from_slice(&data)?;
 
-    Ok(value
-        .into_iter()
-        .map(|(name, v)| (name.clone(), Company { name, statement: v }))
-        .collect())
+    fs::cached_parse(CACHE_PATH, CACHE_VERSION, &data, refresh, |data| {
+        let value: HashMap<String, Fact<String>> = serde_json::from_slice(data)?;
+
+        Ok::<_, Box<dyn Error>>(
+            value
+                .into_iter()
+                .map(|(name, v)| (name.clone(), Company { name, statement: v }))
+                .collect(),
+        )
+    })
 }
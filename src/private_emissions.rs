@@ -1,3 +1,5 @@
+use crate::Leg;
+
 static LITER_PER_GALON: f64 = 3.78541;
 static KG_PER_LITER: f64 = 0.8;
 static EMISSIONS_PER_KG: f64 = 3.16;
@@ -5,6 +7,17 @@ static RADIATIVE_INDEX: f64 = 3.0;
 static LIFE_CYCLE_FACTOR: f64 = 1.68;
 static OCCUPANCY_FACTOR: f64 = 0.23;
 
+/// Below this fraction of a leg's observed peak altitude, a segment counts as "non-cruise" for
+/// [`leg_co2e_kg_from_profile`]: still climbing towards, or already descending from, cruise.
+const CRUISE_ALTITUDE_FRACTION: f64 = 0.9;
+/// Fuel-flow multiplier applied to non-cruise segments while altitude is increasing: reaching
+/// cruise burns noticeably more fuel than holding it, especially on short hops that barely reach
+/// cruise before descending again.
+const CLIMB_FACTOR: f64 = 2.5;
+/// Fuel-flow multiplier applied to non-cruise segments while altitude is decreasing: descent is
+/// typically flown at reduced or idle power.
+const DESCENT_FACTOR: f64 = 0.6;
+
 /// Returns the total CO2e emissions in kg of a private jet with a given
 /// consumption (in GPH) of Jet-A fuel flying for a given amount of time,
 /// as specified in [methodology `M-7`](../methodology.md).
@@ -26,9 +39,43 @@ pub fn leg_per_person(emissions: f64) -> f64 {
     emissions * OCCUPANCY_FACTOR
 }
 
+/// Like [`leg_co2e_kg`], but integrates fuel burn over `leg`'s actual altitude profile instead of
+/// applying `consumption` flat across the whole duration: each `leg.positions()` segment below
+/// [`CRUISE_ALTITUDE_FRACTION`] of the leg's peak altitude is scaled by [`CLIMB_FACTOR`] while
+/// climbing or [`DESCENT_FACTOR`] while descending, so the climb away from (and descent back to)
+/// the ground - the leg's first and last grounded-to-airborne transitions - is weighted
+/// separately from cruise. This keeps short, climb-heavy hops from being underestimated by a
+/// single cruise-rate average.
+///
+/// Deliberately built on [`leg_co2e_kg`]'s GPH-based pipeline rather than
+/// `emissions_private_jet`'s `Specification`/`Fact` model: that module's `Position::Grounded`
+/// variant predates the current [`crate::Position`] and doesn't compile, so there is no working
+/// LTO/`Fact`-source API to extend here. This phase weighting is a coarser, GPH-only
+/// approximation of the same idea, not a port of that API.
+pub fn leg_co2e_kg_from_profile(consumption: f64, leg: &Leg) -> f64 {
+    let positions = leg.positions();
+    let cruise_altitude =
+        positions.iter().map(|p| p.altitude()).fold(0.0, f64::max) * CRUISE_ALTITUDE_FRACTION;
+
+    positions
+        .windows(2)
+        .map(|w| {
+            let phase_factor = if w[0].altitude().min(w[1].altitude()) >= cruise_altitude {
+                1.0
+            } else if w[1].altitude() > w[0].altitude() {
+                CLIMB_FACTOR
+            } else {
+                DESCENT_FACTOR
+            };
+            leg_co2e_kg(consumption * phase_factor, w[1].datetime() - w[0].datetime())
+        })
+        .sum()
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::Position;
 
     #[test]
     fn basics() {
@@ -37,4 +84,46 @@ mod test {
             27009.003313152003
         );
     }
+
+    fn pos(minute: i64, altitude: Option<f64>) -> Position {
+        Position {
+            datetime: time::OffsetDateTime::from_unix_timestamp(minute * 60).unwrap(),
+            latitude: 0.0,
+            longitude: 0.0,
+            altitude,
+        }
+    }
+
+    fn leg(positions: Vec<Position>) -> Leg {
+        Leg { positions }
+    }
+
+    #[test]
+    fn profile_matches_flat_rate_at_constant_cruise() {
+        // constant altitude throughout: every segment is at (>=) cruise, so the profile-aware
+        // computation reduces to the flat-rate one
+        let leg = leg(vec![
+            pos(0, Some(30000.0)),
+            pos(30, Some(30000.0)),
+            pos(60, Some(30000.0)),
+        ]);
+
+        assert_eq!(
+            leg_co2e_kg_from_profile(280.0, &leg),
+            leg_co2e_kg(280.0, leg.duration())
+        );
+    }
+
+    #[test]
+    fn profile_weighs_climb_heavy_hops_more_than_flat_rate() {
+        // short hop that climbs most of the way up and immediately back down: the flat rate
+        // underestimates it relative to the phase-weighted profile
+        let leg = leg(vec![
+            pos(0, Some(1000.0)),
+            pos(5, Some(20000.0)),
+            pos(10, Some(1000.0)),
+        ]);
+
+        assert!(leg_co2e_kg_from_profile(280.0, &leg) > leg_co2e_kg(280.0, leg.duration()));
+    }
 }
@@ -1,4 +1,8 @@
-use std::{collections::HashMap, error::Error, sync::Arc};
+use std::{
+    collections::{HashMap, HashSet},
+    error::Error,
+    sync::Arc,
+};
 
 use itertools::Itertools;
 use time::macros::date;
@@ -8,7 +12,50 @@ use crate::{aircraft::Aircraft, fs::BlobStorageProvider, model::AircraftModel};
 
 pub type RequiredTasks = HashMap<(Arc<str>, time::Date), (Arc<Aircraft>, Arc<AircraftModel>)>;
 
-/// Returns the map `(icao_number, month) -> `[`Aircraft`] for the given set of years and (optionally) countries.
+/// A set of predicates to narrow down [`private_jets_in_month`]'s result: an aircraft must match
+/// every dimension that's non-empty (AND across dimensions), and within a dimension it's enough to
+/// match any one of the set's entries (OR within a dimension). The default `Filter` (every
+/// dimension empty) matches every private jet.
+#[derive(Debug, Clone, Default)]
+pub struct Filter {
+    /// ISO 3166 countries to match; empty matches any country.
+    pub countries: HashSet<String>,
+    /// Aircraft model names to match (e.g. `BEECH 400 Beechjet`, [`AircraftModel::model`]); empty
+    /// matches any model.
+    ///
+    /// This crate doesn't model a separate model-category taxonomy (e.g. "light jet", "heavy
+    /// jet") yet - list the model names that make up the category here until one exists.
+    pub models: HashSet<String>,
+    /// A case-insensitive substring to match against the aircraft's tail number - the closest
+    /// per-aircraft free-text identifier this crate tracks, in the absence of dedicated
+    /// operator/owner data.
+    pub operator_or_owner: Option<String>,
+}
+
+impl Filter {
+    fn matches(&self, aircraft: &Aircraft) -> bool {
+        let country_matches = self.countries.is_empty()
+            || aircraft
+                .country
+                .as_deref()
+                .is_some_and(|country| self.countries.contains(country));
+        let model_matches = self.models.is_empty() || self.models.contains(&aircraft.model);
+        let operator_matches = self
+            .operator_or_owner
+            .as_deref()
+            .map(|needle| {
+                aircraft
+                    .tail_number
+                    .to_lowercase()
+                    .contains(&needle.to_lowercase())
+            })
+            .unwrap_or(true);
+        country_matches && model_matches && operator_matches
+    }
+}
+
+/// Returns the map `(icao_number, month) -> `[`Aircraft`] for the given set of years, restricted to
+/// aircrafts matching `filter`.
 /// The key is the specific `(icao_number, month)`, the value is the [`Aircraft`] associated with that icao_number at that month.
 ///
 /// ## Background
@@ -26,10 +73,10 @@ pub type RequiredTasks = HashMap<(Arc<str>, time::Date), (Arc<Aircraft>, Arc<Air
 /// It leverages these snapshots and the set of aircraft models to return the normalized set of months, aircrafts.
 pub async fn private_jets_in_month(
     years: impl Iterator<Item = i32>,
-    maybe_country: Option<&str>,
+    filter: &Filter,
     client: &dyn BlobStorageProvider,
 ) -> Result<RequiredTasks, Box<dyn Error>> {
-    let models = crate::model::load_private_jet_models()?;
+    let models = crate::model::load_private_jet_models(false)?;
     let aircrafts = crate::aircraft::read_all(client).await?;
 
     // set of icao numbers that are private jets, for each date
@@ -39,13 +86,9 @@ pub async fn private_jets_in_month(
             (
                 date,
                 a.into_iter()
-                    // filter by optional country
-                    .filter(|(_, a)| {
-                        maybe_country
-                            .map(|country| a.country.as_deref() == Some(country))
-                            .unwrap_or(true)
-                    })
-                    // filter for private jet models and optionally country
+                    // filter by countries/models/operator
+                    .filter(|(_, a)| filter.matches(a))
+                    // filter for private jet models
                     .filter_map(|(icao_number, a)| {
                         models
                             .get(&a.model)
@@ -98,6 +141,86 @@ mod test {
 
     use super::*;
 
+    fn aircraft(tail_number: &str, model: &str, country: &str) -> Aircraft {
+        Aircraft {
+            icao_number: tail_number.to_lowercase().into(),
+            tail_number: tail_number.to_string(),
+            type_designator: "F2TH".to_string(),
+            model: model.to_string(),
+            country: Some(country.into()),
+        }
+    }
+
+    #[test]
+    fn filter_default_matches_everything() {
+        let a = aircraft("OY-GFS", "BEECH 400 Beechjet", "DK");
+        assert!(Filter::default().matches(&a));
+    }
+
+    #[test]
+    fn filter_by_country_matches_any_of_the_set() {
+        let pt = aircraft("CS-ABC", "BEECH 400 Beechjet", "PT");
+        let es = aircraft("EC-ABC", "BEECH 400 Beechjet", "ES");
+        let fr = aircraft("F-ABC", "BEECH 400 Beechjet", "FR");
+
+        let filter = Filter {
+            countries: ["PT".to_string(), "ES".to_string()].into(),
+            ..Default::default()
+        };
+        assert!(filter.matches(&pt));
+        assert!(filter.matches(&es));
+        assert!(!filter.matches(&fr));
+    }
+
+    #[test]
+    fn filter_by_model_matches_any_of_the_set() {
+        let beechjet = aircraft("OY-GFS", "BEECH 400 Beechjet", "DK");
+        let citation = aircraft("N123AB", "Cessna Citation X", "US");
+
+        let filter = Filter {
+            models: ["BEECH 400 Beechjet".to_string()].into(),
+            ..Default::default()
+        };
+        assert!(filter.matches(&beechjet));
+        assert!(!filter.matches(&citation));
+    }
+
+    #[test]
+    fn filter_by_operator_or_owner_is_case_insensitive() {
+        let a = aircraft("OY-GFS", "BEECH 400 Beechjet", "DK");
+
+        let filter = Filter {
+            operator_or_owner: Some("oy-g".to_string()),
+            ..Default::default()
+        };
+        assert!(filter.matches(&a));
+
+        let filter = Filter {
+            operator_or_owner: Some("N123AB".to_string()),
+            ..Default::default()
+        };
+        assert!(!filter.matches(&a));
+    }
+
+    #[test]
+    fn combined_filter_dimensions_are_anded() {
+        let a = aircraft("CS-ABC", "BEECH 400 Beechjet", "PT");
+
+        let filter = Filter {
+            countries: ["PT".to_string(), "ES".to_string()].into(),
+            models: ["BEECH 400 Beechjet".to_string()].into(),
+            ..Default::default()
+        };
+        assert!(filter.matches(&a));
+
+        // same countries/models, but the operator substring no longer matches
+        let filter = Filter {
+            operator_or_owner: Some("N123AB".to_string()),
+            ..filter
+        };
+        assert!(!filter.matches(&a));
+    }
+
     #[test]
     fn test_closest_date() {
         assert_eq!(
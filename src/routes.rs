@@ -0,0 +1,269 @@
+//! Treats the legs computed by the `country` binary (endpoints labelled with an airport, e.g.
+//! via `airports.label(pos)`) as a directed graph, so questions like "which
+//! airports are the busiest private-jet hubs in Spain" or "what airports are reachable from
+//! Davos in two hops" can be answered over the aggregated route network instead of per-leg.
+use std::collections::{HashMap, HashSet};
+
+/// Aggregated statistics over every leg observed between two airports.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RouteStats {
+    pub legs: usize,
+    pub distance_km: f64,
+    pub co2e_kg: f64,
+}
+
+/// Per-airport hub centrality: how many distinct routes touch it, how many legs, and how
+/// much CO2e was emitted on those legs. See [`RouteGraph::centrality`].
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct HubCentrality {
+    pub in_degree: usize,
+    pub out_degree: usize,
+    pub legs_in: usize,
+    pub legs_out: usize,
+    pub co2e_kg: f64,
+}
+
+/// The edge weight used to rank paths in [`RouteGraph::shortest_path`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Weight {
+    /// Number of hops, ignoring distance or emissions
+    Hops,
+    /// Total great-circle distance, in km
+    DistanceKm,
+    /// Total private-jet CO2e emissions, in kg
+    Co2eKg,
+}
+
+/// A directed graph of `from_airport -> to_airport` routes, built from a set of legs and
+/// weighted by leg count, total distance and total `leg_co2e_kg`.
+#[derive(Debug, Default, Clone)]
+pub struct RouteGraph {
+    edges: HashMap<(String, String), RouteStats>,
+}
+
+impl RouteGraph {
+    /// Builds a [`RouteGraph`] from `(from_airport, to_airport, distance_km, co2e_kg)` leg
+    /// tuples. Self-loops (legs whose endpoints snapped to the same airport) are skipped, as
+    /// they do not represent a route.
+    pub fn new(legs: impl Iterator<Item = (String, String, f64, f64)>) -> Self {
+        let mut edges: HashMap<(String, String), RouteStats> = HashMap::new();
+        for (from, to, distance_km, co2e_kg) in legs {
+            if from == to {
+                continue;
+            }
+            let stats = edges.entry((from, to)).or_default();
+            stats.legs += 1;
+            stats.distance_km += distance_km;
+            stats.co2e_kg += co2e_kg;
+        }
+        Self { edges }
+    }
+
+    fn adjacency(&self) -> HashMap<&str, Vec<(&str, &RouteStats)>> {
+        let mut adjacency: HashMap<&str, Vec<(&str, &RouteStats)>> = HashMap::new();
+        for ((from, to), stats) in &self.edges {
+            adjacency
+                .entry(from.as_str())
+                .or_default()
+                .push((to.as_str(), stats));
+        }
+        adjacency
+    }
+
+    /// Returns the set of airports reachable from `origin` (excluding `origin` itself).
+    /// # Implementation
+    /// Computed as the transitive closure of the edge set via a semi-naive fixpoint: seeded
+    /// with `origin`'s direct successors, each round follows the edges out of only the airports
+    /// first reached in the previous round (`reachable[a,c] ∧ edge[c,b] ⇒ reachable[a,b]`), and
+    /// stops once a round reaches nothing new.
+    pub fn reachable_from(&self, origin: &str) -> HashSet<String> {
+        let mut reachable: HashSet<String> = self
+            .edges
+            .keys()
+            .filter(|(from, _)| from == origin)
+            .map(|(_, to)| to.clone())
+            .collect();
+        let mut frontier: Vec<String> = reachable.iter().cloned().collect();
+
+        loop {
+            let joined: Vec<String> = frontier
+                .iter()
+                .flat_map(|c| {
+                    self.edges
+                        .keys()
+                        .filter(move |(from, _)| from == c)
+                        .map(|(_, to)| to.clone())
+                })
+                .filter(|to| to != origin && !reachable.contains(to))
+                .collect();
+
+            if joined.is_empty() {
+                break;
+            }
+            reachable.extend(joined.iter().cloned());
+            frontier = joined;
+        }
+
+        reachable
+    }
+
+    /// Returns the shortest path between `from` and `to` ranked by `weight`, as the ordered
+    /// list of airports visited (including both endpoints) and the accumulated weight.
+    /// Returns `None` if `to` is unreachable from `from`.
+    /// # Implementation
+    /// Dijkstra's algorithm over the weighted adjacency map.
+    pub fn shortest_path(&self, from: &str, to: &str, weight: Weight) -> Option<(Vec<String>, f64)> {
+        if from == to {
+            return Some((vec![from.to_string()], 0.0));
+        }
+
+        let adjacency = self.adjacency();
+
+        let mut dist: HashMap<&str, f64> = HashMap::from([(from, 0.0)]);
+        let mut prev: HashMap<&str, &str> = HashMap::new();
+        let mut visited: HashSet<&str> = HashSet::new();
+
+        while let Some((current, cost)) = dist
+            .iter()
+            .filter(|(node, _)| !visited.contains(*node))
+            .min_by(|(_, a), (_, b)| a.total_cmp(b))
+            .map(|(node, cost)| (*node, *cost))
+        {
+            if current == to {
+                break;
+            }
+            visited.insert(current);
+
+            for (neighbour, stats) in adjacency.get(current).into_iter().flatten() {
+                let edge_weight = match weight {
+                    Weight::Hops => 1.0,
+                    Weight::DistanceKm => stats.distance_km,
+                    Weight::Co2eKg => stats.co2e_kg,
+                };
+                let candidate = cost + edge_weight;
+                if candidate < *dist.get(neighbour).unwrap_or(&f64::INFINITY) {
+                    dist.insert(neighbour, candidate);
+                    prev.insert(neighbour, current);
+                }
+            }
+        }
+
+        let total = *dist.get(to)?;
+
+        let mut path = vec![to];
+        while let Some(&node) = prev.get(path.last().unwrap()) {
+            path.push(node);
+        }
+        path.reverse();
+
+        Some((path.into_iter().map(str::to_string).collect(), total))
+    }
+
+    /// Per-airport hub centrality (in/out degree and total emissions routed through it), to
+    /// rank the busiest private-jet hubs.
+    pub fn centrality(&self) -> HashMap<String, HubCentrality> {
+        let mut centrality: HashMap<String, HubCentrality> = HashMap::new();
+
+        for ((from, to), stats) in &self.edges {
+            let out = centrality.entry(from.clone()).or_default();
+            out.out_degree += 1;
+            out.legs_out += stats.legs;
+            out.co2e_kg += stats.co2e_kg;
+
+            let inn = centrality.entry(to.clone()).or_default();
+            inn.in_degree += 1;
+            inn.legs_in += stats.legs;
+            inn.co2e_kg += stats.co2e_kg;
+        }
+
+        centrality
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn graph() -> RouteGraph {
+        // davos -> zurich -> geneva, davos -> geneva (direct, more expensive), plus a self-loop
+        RouteGraph::new(
+            vec![
+                ("davos".to_string(), "zurich".to_string(), 100.0, 500.0),
+                ("zurich".to_string(), "geneva".to_string(), 250.0, 900.0),
+                ("davos".to_string(), "geneva".to_string(), 400.0, 2000.0),
+                ("geneva".to_string(), "geneva".to_string(), 0.0, 0.0),
+            ]
+            .into_iter(),
+        )
+    }
+
+    #[test]
+    fn skips_self_loops() {
+        let graph = graph();
+        assert_eq!(graph.edges.len(), 3);
+    }
+
+    #[test]
+    fn reachable_from_transitive_closure() {
+        let graph = graph();
+        assert_eq!(
+            graph.reachable_from("davos"),
+            HashSet::from(["zurich".to_string(), "geneva".to_string()])
+        );
+        assert!(graph.reachable_from("geneva").is_empty());
+    }
+
+    #[test]
+    fn shortest_path_by_hops_prefers_direct_edge() {
+        let graph = graph();
+        let (path, hops) = graph.shortest_path("davos", "geneva", Weight::Hops).unwrap();
+        assert_eq!(path, vec!["davos".to_string(), "geneva".to_string()]);
+        assert_eq!(hops, 1.0);
+    }
+
+    #[test]
+    fn shortest_path_by_distance_prefers_two_hops() {
+        let graph = graph();
+        let (path, km) = graph
+            .shortest_path("davos", "geneva", Weight::DistanceKm)
+            .unwrap();
+        assert_eq!(
+            path,
+            vec!["davos".to_string(), "zurich".to_string(), "geneva".to_string()]
+        );
+        assert_eq!(km, 350.0);
+    }
+
+    #[test]
+    fn shortest_path_unreachable_is_none() {
+        let graph = graph();
+        assert_eq!(graph.shortest_path("geneva", "davos", Weight::Hops), None);
+    }
+
+    #[test]
+    fn empty_graph() {
+        let graph = RouteGraph::new(std::iter::empty());
+        assert!(graph.reachable_from("davos").is_empty());
+        assert_eq!(graph.shortest_path("davos", "geneva", Weight::Hops), None);
+        assert!(graph.centrality().is_empty());
+    }
+
+    #[test]
+    fn centrality_counts_degree_and_emissions() {
+        let graph = graph();
+        let centrality = graph.centrality();
+
+        let davos = centrality.get("davos").unwrap();
+        assert_eq!(davos.out_degree, 2);
+        assert_eq!(davos.in_degree, 0);
+        assert_eq!(davos.co2e_kg, 500.0 + 2000.0);
+
+        let zurich = centrality.get("zurich").unwrap();
+        assert_eq!(zurich.in_degree, 1);
+        assert_eq!(zurich.out_degree, 1);
+
+        let geneva = centrality.get("geneva").unwrap();
+        assert_eq!(geneva.in_degree, 2);
+        assert_eq!(geneva.out_degree, 0);
+    }
+}
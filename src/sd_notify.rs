@@ -0,0 +1,47 @@
+//! A minimal client for systemd's `sd_notify(3)` protocol: sends `READY=1`/`STATUS=...`/
+//! `WATCHDOG=1` datagrams to the `AF_UNIX` socket named by `$NOTIFY_SOCKET`, so a long-running ETL
+//! can report liveness and progress to a service manager. Every function is a no-op when
+//! `$NOTIFY_SOCKET` is unset, so runs outside systemd (e.g. on a laptop) are unaffected.
+use std::time::Duration;
+
+fn notify(message: &str) -> std::io::Result<()> {
+    let Some(socket_path) = std::env::var_os("NOTIFY_SOCKET") else {
+        return Ok(());
+    };
+
+    #[cfg(unix)]
+    {
+        let socket = std::os::unix::net::UnixDatagram::unbound()?;
+        socket.send_to(message.as_bytes(), socket_path)?;
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = socket_path;
+    }
+    Ok(())
+}
+
+/// Tells the service manager this process has finished starting up.
+pub fn ready() -> std::io::Result<()> {
+    notify("READY=1")
+}
+
+/// Reports free-form progress text, surfaced by e.g. `systemctl status`.
+pub fn status(message: &str) -> std::io::Result<()> {
+    notify(&format!("STATUS={message}"))
+}
+
+/// Pings the watchdog, proving this process is still alive and not hung.
+pub fn watchdog() -> std::io::Result<()> {
+    notify("WATCHDOG=1")
+}
+
+/// The interval at which [`watchdog`] should be pinged, derived from the `$WATCHDOG_USEC` systemd
+/// sets when a unit has `WatchdogSec=` configured. Halved per `sd_notify(3)`'s recommendation to
+/// ping at least twice within the configured timeout. `None` when no watchdog is configured (or
+/// `$NOTIFY_SOCKET` is unset), in which case callers should skip scheduling pings entirely.
+pub fn watchdog_interval() -> Option<Duration> {
+    std::env::var_os("NOTIFY_SOCKET")?;
+    let watchdog_usec: u64 = std::env::var("WATCHDOG_USEC").ok()?.parse().ok()?;
+    Some(Duration::from_micros(watchdog_usec) / 2)
+}
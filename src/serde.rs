@@ -19,6 +19,25 @@ pub fn parse_month(date: &str) -> time::Date {
     .unwrap()
 }
 
+/// Like [`parse_month`], but returns a `Result` instead of panicking - for callers parsing a
+/// month string that didn't come from this crate's own blob names/manifests (e.g. a remote
+/// client's request), where a malformed value is an input error, not a programmer error.
+pub fn try_parse_month(date: &str) -> Result<time::Date, String> {
+    let year = date
+        .get(..4)
+        .ok_or_else(|| format!("expected a \"YYYY-MM\" month, got {date:?}"))?
+        .parse::<i32>()
+        .map_err(|e| format!("invalid year in {date:?}: {e}"))?;
+    let month = date
+        .get(5..7)
+        .ok_or_else(|| format!("expected a \"YYYY-MM\" month, got {date:?}"))?
+        .parse::<u8>()
+        .map_err(|e| format!("invalid month in {date:?}: {e}"))?
+        .try_into()
+        .map_err(|e| format!("invalid month in {date:?}: {e}"))?;
+    time::Date::from_calendar_date(year, month, 1).map_err(|e| format!("invalid date: {e}"))
+}
+
 pub fn hive_to_map<'a>(mut blob: &'a str) -> HashMap<&'a str, &'a str> {
     let mut a = HashMap::new();
     while !blob.is_empty() {
@@ -42,4 +61,19 @@ mod test {
         let a = hive_to_map("a=1/b=2/");
         assert_eq!(a, vec![("a", "1"), ("b", "2")].into_iter().collect());
     }
+
+    #[test]
+    fn try_parse_month_matches_parse_month() {
+        assert_eq!(
+            try_parse_month("2022-01").unwrap(),
+            parse_month("2022-01")
+        );
+    }
+
+    #[test]
+    fn try_parse_month_rejects_malformed_input() {
+        assert!(try_parse_month("x").is_err());
+        assert!(try_parse_month("2022-13").is_err());
+        assert!(try_parse_month("20xx-01").is_err());
+    }
 }
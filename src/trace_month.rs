@@ -8,6 +8,11 @@ use crate::{cached_aircraft_positions, fs, BlobStorageProvider};
 
 static DATABASE: &'static str = "position/";
 
+/// Bump whenever `Position`'s serialization changes, so every previously cached
+/// `data.json` blob is treated as a miss and re-fetched instead of silently
+/// deserializing into the wrong shape.
+const POSITIONS_CACHE_VERSION: u32 = 0;
+
 fn pk_to_blob_name(icao: &str, date: time::Date) -> String {
     format!(
         "{DATABASE}icao_number={icao}/month={}/data.json",
@@ -69,7 +74,7 @@ pub async fn month_positions(
         Ok(bytes)
     };
 
-    let r = fs::cached_call(&blob_name, fetch, client, action).await?;
+    let r = fs::cached_call(&blob_name, fetch, client, action, POSITIONS_CACHE_VERSION).await?;
     Ok(serde_json::from_slice(&r)?)
 }
 
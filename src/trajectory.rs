@@ -0,0 +1,152 @@
+//! Exports computed [`Leg`]s as GeoJSON or a flat, GTFS-like trip/stop-times table, so downstream
+//! mapping/transit tooling can consume trajectories directly instead of the ad-hoc CSV log lines
+//! the binaries print today. Both writers write directly into an [`io::Write`] one leg (or row)
+//! at a time, so a large multi-month dataset never needs to be fully buffered in memory.
+use std::io::{self, Write};
+
+use crate::Leg;
+
+const DATETIME_FORMAT: &[time::format_description::FormatItem<'static>] =
+    time::macros::format_description!("[year]-[month]-[day]T[hour]:[minute]:[second]Z");
+
+/// Escapes `text` for use inside a JSON string literal: backslash, double quote and control
+/// characters are backslash-escaped, per RFC 8259 section 7.
+fn json_escape(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+        .replace('\r', "\\r")
+        .replace('\t', "\\t")
+}
+
+/// Writes `legs` (all flown by `icao_number`) as a GeoJSON `FeatureCollection`: one `LineString`
+/// feature per leg, carrying `icao_number`, `datetime` (of the leg's first position),
+/// `maximum_altitude` and `distance` properties.
+pub fn to_geojson(icao_number: &str, legs: &[Leg], writer: &mut impl Write) -> io::Result<()> {
+    write!(writer, r#"{{"type":"FeatureCollection","features":["#)?;
+    for (i, leg) in legs.iter().enumerate() {
+        if i > 0 {
+            write!(writer, ",")?;
+        }
+
+        write!(
+            writer,
+            r#"{{"type":"Feature","geometry":{{"type":"LineString","coordinates":["#
+        )?;
+        for (j, position) in leg.positions().iter().enumerate() {
+            if j > 0 {
+                write!(writer, ",")?;
+            }
+            write!(writer, "[{},{}]", position.longitude(), position.latitude())?;
+        }
+
+        let maximum_altitude = leg
+            .positions()
+            .iter()
+            .map(|position| position.altitude())
+            .fold(0.0, f64::max);
+        let datetime = leg
+            .from()
+            .datetime()
+            .format(DATETIME_FORMAT)
+            .expect("valid timestamp");
+
+        write!(
+            writer,
+            r#"]}},"properties":{{"icao_number":"{}","datetime":"{}","maximum_altitude":{maximum_altitude},"distance":{}}}}}"#,
+            json_escape(icao_number),
+            datetime,
+            leg.distance(),
+        )?;
+    }
+    write!(writer, "]}}")
+}
+
+/// Writes `legs` (all flown by `icao_number`) as a flat, GTFS-like trip/stop-times table: one CSV
+/// row per sampled position, with `trip_id` identifying the leg (`{icao_number}-{leg start unix
+/// timestamp}`), `stop_sequence` giving the position's order within the leg, and
+/// `arrival_time`/`departure_time` both set to the position's timestamp, since a leg is built
+/// from point samples rather than dwell-time stops.
+pub fn to_gtfs(icao_number: &str, legs: &[Leg], writer: &mut impl Write) -> io::Result<()> {
+    writeln!(
+        writer,
+        "trip_id,stop_sequence,stop_id,arrival_time,departure_time,latitude,longitude,altitude"
+    )?;
+    for leg in legs {
+        let trip_id = format!("{icao_number}-{}", leg.from().datetime().unix_timestamp());
+        for (stop_sequence, position) in leg.positions().iter().enumerate() {
+            let timestamp = position
+                .datetime()
+                .format(DATETIME_FORMAT)
+                .expect("valid timestamp");
+            writeln!(
+                writer,
+                "{trip_id},{stop_sequence},{trip_id}-{stop_sequence},{timestamp},{timestamp},{},{},{}",
+                position.latitude(),
+                position.longitude(),
+                position.altitude(),
+            )?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn pos(t: i64, lat: f64, lon: f64, altitude: Option<f64>) -> crate::Position {
+        crate::Position {
+            datetime: time::OffsetDateTime::from_unix_timestamp(t).unwrap(),
+            latitude: lat,
+            longitude: lon,
+            altitude,
+        }
+    }
+
+    fn leg() -> Leg {
+        // Positions less than 5 minutes apart (a longer gap close to the ground counts as a
+        // landing) but spanning more than 5 minutes in total, so the leg survives `legs()`'s noise
+        // filter (legs no longer than 5 minutes are discarded).
+        crate::legs(
+            vec![
+                pos(0, 52.0, 4.0, None),
+                pos(200, 52.5, 4.5, Some(30000.0)),
+                pos(400, 48.0, 2.0, None),
+            ]
+            .into_iter(),
+        )
+        .next()
+        .unwrap()
+    }
+
+    #[test]
+    fn geojson_renders_one_linestring_per_leg() {
+        let mut out = Vec::new();
+        to_geojson("45860d", &[leg()], &mut out).unwrap();
+        let out = String::from_utf8(out).unwrap();
+
+        assert!(out.starts_with(r#"{"type":"FeatureCollection","features":[{"type":"Feature""#));
+        assert!(out.contains(r#""type":"LineString""#));
+        assert!(out.contains(r#""icao_number":"45860d""#));
+        assert!(out.contains(r#""maximum_altitude":30000"#));
+        assert!(out.ends_with("]}"));
+
+        let _: serde_json::Value = serde_json::from_str(&out).unwrap();
+    }
+
+    #[test]
+    fn gtfs_emits_one_stop_time_row_per_position() {
+        let mut out = Vec::new();
+        to_gtfs("45860d", &[leg()], &mut out).unwrap();
+        let out = String::from_utf8(out).unwrap();
+
+        let mut lines = out.lines();
+        assert_eq!(
+            lines.next().unwrap(),
+            "trip_id,stop_sequence,stop_id,arrival_time,departure_time,latitude,longitude,altitude"
+        );
+        assert_eq!(lines.clone().count(), 3);
+        assert!(lines.next().unwrap().starts_with("45860d-0,0,45860d-0-0,"));
+    }
+}
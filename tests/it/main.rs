@@ -139,7 +139,8 @@ async fn gets_db_month() -> Result<(), Box<dyn Error>> {
 async fn private_jets_in_month() -> Result<(), Box<dyn Error>> {
     let client = flights::fs_s3::anonymous_client().await;
 
-    let aircraft = flights::private_jets_in_month(2022..2024, None, &client).await?;
+    let aircraft =
+        flights::private_jets_in_month(2022..2024, &flights::Filter::default(), &client).await?;
 
     // this number should be constant, as the db of aircrafts does not change in the past
     assert_eq!(aircraft.len(), 29425 * 24);